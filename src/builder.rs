@@ -0,0 +1,621 @@
+use crate::crc::crc32;
+use crate::encode::{
+    encode, select_format, AlphaContent, AutoFormatOptions, DxtQuality, EncodeOptions,
+};
+use crate::flags::{ExtendedFlags, TextureFlags};
+use crate::header::VTFHeader;
+use crate::heightmap::{height_to_normal, height_to_ssbump, HeightMapOptions};
+use crate::image::ImageFormat;
+use crate::keyvalues::KeyValues;
+use crate::mipmap::{
+    generate_mipmaps, mip_dimensions, mipmap_count, AddressMode, MipmapOptions, NormalMapOptions,
+};
+use crate::reflectivity::{compute_reflectivity, ReflectivityOptions};
+use crate::resize::ResizePolicy;
+use crate::resources::{LodControl, Resource, ResourceList, ResourceType};
+use crate::sheet::Sheet;
+use crate::vtex::CompileParams;
+use crate::Error;
+use image::imageops::FilterType;
+use image::DynamicImage;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::io::Write;
+use std::path::Path;
+use std::vec::Vec;
+
+/// Builder for creating vtf files with full control over the header
+///
+/// The defaults match the output of [`VTF::create`](crate::vtf::VTF::create).
+///
+/// Resources (crc, LOD control, extended flags, KeyValues and particle sheets) need
+/// version 7.3 or newer, building an older version with any of them set fails.
+#[derive(Debug, Clone)]
+pub struct VTFBuilder {
+    frames: Vec<DynamicImage>,
+    format: ImageFormat,
+    auto_format: Option<AutoFormatOptions>,
+    version: [u32; 2],
+    flags: TextureFlags,
+    first_frame: u16,
+    reflectivity: Option<[f32; 3]>,
+    reflectivity_options: ReflectivityOptions,
+    bumpmap_scale: f32,
+    mipmaps: bool,
+    mipmap_options: MipmapOptions,
+    gamma_correct: Option<bool>,
+    address_mode: Option<(AddressMode, AddressMode)>,
+    normal_map: Option<NormalMapOptions>,
+    ssbump: bool,
+    thumbnail: bool,
+    crc: Option<u32>,
+    lod_control: Option<LodControl>,
+    extended_flags: Option<ExtendedFlags>,
+    key_values: Option<KeyValues>,
+    sheet: Option<Sheet>,
+    encode_options: EncodeOptions,
+    resize_policy: ResizePolicy,
+}
+
+impl VTFBuilder {
+    /// Largest size of the low resolution thumbnail in either direction
+    const THUMBNAIL_SIZE: u32 = 16;
+
+    const DEFAULT_FLAGS: TextureFlags = TextureFlags::CLAMPS
+        .union(TextureFlags::CLAMPT)
+        .union(TextureFlags::NOMIP)
+        .union(TextureFlags::NOLOD)
+        .union(TextureFlags::EIGHTBITALPHA);
+
+    pub fn new(image: DynamicImage, format: ImageFormat) -> Self {
+        VTFBuilder {
+            frames: vec![image],
+            format,
+            auto_format: None,
+            version: [7, 1],
+            flags: Self::DEFAULT_FLAGS,
+            first_frame: 0,
+            reflectivity: None,
+            reflectivity_options: ReflectivityOptions::default(),
+            bumpmap_scale: 1.0,
+            mipmaps: false,
+            mipmap_options: MipmapOptions::default(),
+            gamma_correct: None,
+            address_mode: None,
+            normal_map: None,
+            ssbump: false,
+            thumbnail: false,
+            crc: None,
+            lod_control: None,
+            extended_flags: None,
+            key_values: None,
+            sheet: None,
+            encode_options: EncodeOptions::default(),
+            resize_policy: ResizePolicy::default(),
+        }
+    }
+
+    /// Pick the format from the image content when building, see
+    /// [`select_format`](crate::encode::select_format)
+    ///
+    /// The `ONEBITALPHA` and `EIGHTBITALPHA` flags are set to match the alpha channel.
+    pub fn auto(image: DynamicImage, options: AutoFormatOptions) -> Self {
+        let mut builder = Self::new(image, ImageFormat::Rgba8888);
+        builder.auto_format = Some(options);
+        builder
+    }
+
+    /// Create a normal map from a grayscale height map
+    pub fn normal_from_height(
+        height: &DynamicImage,
+        format: ImageFormat,
+        options: &HeightMapOptions,
+    ) -> Self {
+        Self::new(height_to_normal(height, options), format).normal_map(NormalMapOptions::default())
+    }
+
+    /// Create a self shadowing bump map from a grayscale height map, setting the `SSBUMP` flag
+    pub fn ssbump_from_height(
+        height: &DynamicImage,
+        format: ImageFormat,
+        options: &HeightMapOptions,
+    ) -> Self {
+        let mut builder = Self::new(height_to_ssbump(height, options), format);
+        builder.ssbump = true;
+        builder
+    }
+
+    /// Load a texture the way vtex compiles it
+    ///
+    /// `path` is either the source image or its `.txt` parameter file, the parameter file next
+    /// to a source image is used when it exists. The checksum of the source files is stored as
+    /// CRC resource, so the version is set to 7.5.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let path = path.as_ref();
+        let params = if path
+            .extension()
+            .map_or(false, |extension| extension.eq_ignore_ascii_case("txt"))
+        {
+            CompileParams::parse(&std::fs::read_to_string(path)?)?
+        } else {
+            CompileParams::for_image(path)?.unwrap_or_default()
+        };
+
+        let mut source = Vec::new();
+        let mut frames = Vec::new();
+        for path in params.source_paths(path)? {
+            let bytes = std::fs::read(&path)?;
+            frames.push(image::load_from_memory_with_format(
+                &bytes,
+                image::ImageFormat::from_path(&path)?,
+            )?);
+            source.extend_from_slice(&bytes);
+        }
+
+        let mut builder = Self::new(frames[0].clone(), ImageFormat::Dxt1);
+        builder.frames = frames;
+        Ok(builder
+            .version([7, 5])
+            .crc(crc32(&source))
+            .compile_params(&params))
+    }
+
+    /// Apply the settings from a vtex parameter file, replacing the format and flags
+    ///
+    /// Like vtex the alpha values of the frames are checked, textures that aren't fully opaque
+    /// are stored as DXT5, or BGRA8888 with `nocompress`, and get the matching alpha flag.
+    pub fn compile_params(mut self, params: &CompileParams) -> Self {
+        let alpha = AlphaContent::of(&self.frames);
+        self.auto_format = None;
+        self.format = match (params.nocompress, alpha) {
+            (false, AlphaContent::Opaque) => ImageFormat::Dxt1,
+            (false, _) => ImageFormat::Dxt5,
+            (true, AlphaContent::Opaque) => ImageFormat::Bgr888,
+            (true, _) => ImageFormat::Bgra8888,
+        };
+        self.flags = params.flags() | alpha.flags();
+        self.mipmaps = !params.nomip;
+        self.thumbnail = true;
+        if let Some(bumpscale) = params.bumpscale {
+            self.bumpmap_scale = bumpscale;
+        }
+        if params.normal {
+            self.normal_map = Some(self.normal_map.unwrap_or_default());
+        }
+        self.ssbump |= params.ssbump;
+        self
+    }
+
+    /// Add an additional animation frame, all frames need to have the same size
+    ///
+    /// With the `ENVMAP` flag set every image is a face of the cube map, in the order right,
+    /// left, back, front, up, down and the spheremap for versions before 7.5 unless the first
+    /// frame is set to `0xffff`. A single image is used for all faces.
+    pub fn frame(mut self, image: DynamicImage) -> Self {
+        self.frames.push(image);
+        self
+    }
+
+    pub fn format(mut self, format: ImageFormat) -> Self {
+        self.format = format;
+        self.auto_format = None;
+        self
+    }
+
+    pub fn version(mut self, version: [u32; 2]) -> Self {
+        self.version = version;
+        self
+    }
+
+    pub fn flags(mut self, flags: TextureFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    pub fn first_frame(mut self, first_frame: u16) -> Self {
+        self.first_frame = first_frame;
+        self
+    }
+
+    /// Use a fixed reflectivity instead of computing it from the image
+    pub fn reflectivity(mut self, reflectivity: [f32; 3]) -> Self {
+        self.reflectivity = Some(reflectivity);
+        self
+    }
+
+    pub fn reflectivity_options(mut self, options: ReflectivityOptions) -> Self {
+        self.reflectivity_options = options;
+        self
+    }
+
+    pub fn bumpmap_scale(mut self, bumpmap_scale: f32) -> Self {
+        self.bumpmap_scale = bumpmap_scale;
+        self
+    }
+
+    /// Generate a full mipmap chain instead of only storing the full size image
+    pub fn mipmaps(mut self, mipmaps: bool) -> Self {
+        self.mipmaps = mipmaps;
+        self
+    }
+
+    /// Settings for the mipmap chain, unset fields follow the flags and other builder settings
+    pub fn mipmap_options(mut self, options: MipmapOptions) -> Self {
+        self.mipmap_options = options;
+        self
+    }
+
+    /// Linearize color before filtering mipmaps and computing the reflectivity
+    ///
+    /// By default mipmaps are only linearized for textures with the sRGB flag set, while the
+    /// reflectivity is always linearized. The `gamma_correct` field of
+    /// [`MipmapOptions`] or [`ReflectivityOptions`] overrides this for one of them.
+    pub fn gamma_correct(mut self, gamma_correct: bool) -> Self {
+        self.gamma_correct = Some(gamma_correct);
+        self
+    }
+
+    /// Edge handling when filtering mipmaps in the horizontal and vertical direction
+    ///
+    /// By default clamped directions are taken from the `CLAMPS` and `CLAMPT` flags and all
+    /// other directions wrap.
+    pub fn address_mode(mut self, u: AddressMode, v: AddressMode) -> Self {
+        self.address_mode = Some((u, v));
+        self
+    }
+
+    /// Treat the image as a tangent space normal map
+    ///
+    /// Mipmaps are renormalized after filtering and the `NORMAL` flag is set.
+    pub fn normal_map(mut self, options: NormalMapOptions) -> Self {
+        self.normal_map = Some(options);
+        self
+    }
+
+    /// Store a low resolution DXT1 thumbnail of the first frame
+    pub fn thumbnail(mut self, thumbnail: bool) -> Self {
+        self.thumbnail = thumbnail;
+        self
+    }
+
+    /// Store a CRC resource with the checksum of the source image, see [`crc32`](crate::crc::crc32)
+    pub fn crc(mut self, crc: u32) -> Self {
+        self.crc = Some(crc);
+        self
+    }
+
+    /// Store a LOD control resource clamping the loaded resolution
+    pub fn lod_control(mut self, lod_control: LodControl) -> Self {
+        self.lod_control = Some(lod_control);
+        self
+    }
+
+    /// Store an extended flags resource
+    pub fn extended_flags(mut self, flags: ExtendedFlags) -> Self {
+        self.extended_flags = Some(flags);
+        self
+    }
+
+    /// Embed KeyValues data in a KVD resource
+    ///
+    /// Building fails when a key or value contains a quote, see [`KeyValues::serialize`].
+    pub fn key_values(mut self, key_values: KeyValues) -> Self {
+        self.key_values = Some(key_values);
+        self
+    }
+
+    /// Store a particle sheet describing the sprites in the image
+    pub fn sheet(mut self, sheet: Sheet) -> Self {
+        self.sheet = Some(sheet);
+        self
+    }
+
+    pub fn encode_options(mut self, options: EncodeOptions) -> Self {
+        self.encode_options = options;
+        self
+    }
+
+    /// Trade speed for quality when compressing DXT formats
+    pub fn dxt_quality(mut self, quality: DxtQuality) -> Self {
+        self.encode_options.dxt_quality = quality;
+        self
+    }
+
+    /// How to handle images that don't have a power of two size
+    pub fn resize_policy(mut self, policy: ResizePolicy) -> Self {
+        self.resize_policy = policy;
+        self
+    }
+
+    pub fn build(&self) -> Result<Vec<u8>, Error> {
+        let mut data = Vec::new();
+        self.write(&mut data)?;
+        Ok(data)
+    }
+
+    pub fn write(&self, writer: &mut impl Write) -> Result<(), Error> {
+        if self.version[0] != 7 || self.version[1] > 5 {
+            return Err(Error::UnsupportedVersion(self.version[0], self.version[1]));
+        }
+
+        let has_resources = self.crc.is_some()
+            || self.lod_control.is_some()
+            || self.extended_flags.is_some()
+            || self.key_values.is_some()
+            || self.sheet.is_some();
+        if has_resources && self.version[1] < 3 {
+            return Err(Error::ResourcesNotSupported(
+                self.version[0],
+                self.version[1],
+            ));
+        }
+
+        if self.frames.iter().any(|frame| {
+            frame.width() != self.frames[0].width() || frame.height() != self.frames[0].height()
+        }) {
+            return Err(Error::MismatchedFrameSize);
+        }
+
+        let mut frames = self
+            .frames
+            .iter()
+            .map(|frame| self.resize_policy.apply(frame))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // environment maps store every face of a frame as a separate image
+        let faces = VTFHeader::face_count(self.version, self.flags, self.first_frame) as usize;
+        if faces > 1 && frames.len() == 1 {
+            frames = vec![frames[0].clone(); faces];
+        } else if frames.len() % faces != 0 {
+            return Err(Error::MismatchedFaceCount(frames.len(), faces as u32));
+        }
+        let width = frames[0].width();
+        let height = frames[0].height();
+
+        let mut flags = self.flags;
+        let format = match &self.auto_format {
+            Some(options) => {
+                flags.remove(TextureFlags::ONEBITALPHA | TextureFlags::EIGHTBITALPHA);
+                flags |= AlphaContent::of(&frames).flags();
+                select_format(&frames, options)
+            }
+            None => self.format,
+        };
+
+        let mipmap_count = if self.mipmaps {
+            mipmap_count(width, height)
+        } else {
+            1
+        };
+        let (lowres_width, lowres_height) = if self.thumbnail {
+            thumbnail_dimensions(width, height)
+        } else {
+            (0, 0)
+        };
+
+        let mipmap_gamma = self.mipmap_options.gamma_correct.or(self.gamma_correct);
+        let reflectivity_options = ReflectivityOptions {
+            gamma_correct: self
+                .reflectivity_options
+                .gamma_correct
+                .or(self.gamma_correct),
+            ..self.reflectivity_options
+        };
+        let address_u = self
+            .address_mode
+            .map(|(u, _)| u)
+            .or(self.mipmap_options.address_u)
+            .unwrap_or_else(|| address_mode_for_flag(self.flags, TextureFlags::CLAMPS));
+        let address_v = self
+            .address_mode
+            .map(|(_, v)| v)
+            .or(self.mipmap_options.address_v)
+            .unwrap_or_else(|| address_mode_for_flag(self.flags, TextureFlags::CLAMPT));
+        let mipmap_options = MipmapOptions {
+            gamma_correct: Some(mipmap_gamma.unwrap_or(self.flags.contains(TextureFlags::SRGB))),
+            address_u: Some(address_u),
+            address_v: Some(address_v),
+            normal_map: self.normal_map.or(self.mipmap_options.normal_map),
+            ..self.mipmap_options
+        };
+        if let Some(cutoff) = mipmap_options.alpha_coverage {
+            if !(cutoff > 0.0 && cutoff < 1.0) {
+                return Err(Error::InvalidAlphaCutoff(cutoff));
+            }
+        }
+        // the DXT1 encoder makes alpha below 0.5 transparent, so apply the cutoff up front
+        let one_bit_cutoff = mipmap_options
+            .alpha_coverage
+            .filter(|_| format == ImageFormat::Dxt1Onebitalpha);
+        if mipmap_options.normal_map.is_some() {
+            flags |= TextureFlags::NORMAL;
+        }
+        if self.ssbump {
+            flags |= TextureFlags::SSBUMP;
+        }
+
+        let mut header = VTFHeader {
+            signature: VTFHeader::SIGNATURE,
+            version: self.version,
+            header_size: 0,
+            width: width as u16,
+            height: height as u16,
+            flags,
+            frames: (frames.len() / faces) as u16,
+            first_frame: self.first_frame,
+            reflectivity: self
+                .reflectivity
+                .unwrap_or_else(|| compute_reflectivity(&frames, &reflectivity_options)),
+            bumpmap_scale: self.bumpmap_scale,
+            highres_image_format: format,
+            mipmap_count,
+            lowres_image_format: ImageFormat::Dxt1,
+            lowres_image_width: lowres_width as u8,
+            lowres_image_height: lowres_height as u8,
+            depth: 1,
+            resources: ResourceList::empty(),
+        };
+
+        // resources with a data chunk, stored between the header and the images
+        let mut chunks = Vec::new();
+
+        // 7.3 and up locate the image data through the resource directory
+        if self.version[1] >= 3 {
+            if self.thumbnail {
+                header.resources.resources.push(Resource::new(
+                    ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE,
+                    0,
+                ));
+            }
+            header
+                .resources
+                .resources
+                .push(Resource::new(ResourceType::VTF_LEGACY_RSRC_IMAGE, 0));
+            if let Some(crc) = self.crc {
+                header
+                    .resources
+                    .resources
+                    .push(Resource::new(ResourceType::CRC, crc));
+            }
+            if let Some(lod_control) = self.lod_control {
+                header
+                    .resources
+                    .resources
+                    .push(Resource::new(ResourceType::LOD_CONTROL, lod_control.into()));
+            }
+            if let Some(extended_flags) = self.extended_flags {
+                header.resources.resources.push(Resource::new(
+                    ResourceType::EXTENDED_FLAGS,
+                    extended_flags.bits(),
+                ));
+            }
+            if let Some(key_values) = &self.key_values {
+                chunks.push((
+                    ResourceType::KEY_VALUES,
+                    key_values.serialize()?.into_bytes(),
+                ));
+            }
+            if let Some(sheet) = &self.sheet {
+                let mut data = Vec::new();
+                sheet.write(&mut data)?;
+                chunks.push((ResourceType::SHEET, data));
+            }
+            for (ty, _) in &chunks {
+                header.resources.resources.push(Resource::new(*ty, 0));
+            }
+        }
+        header.header_size = header.size() as u32;
+
+        let mut offset = header.header_size;
+        for (ty, chunk) in &chunks {
+            if let Some(resource) = header.resources.get_by_type_mut(*ty) {
+                resource.data = offset;
+            }
+            offset += chunk.len() as u32 + 4;
+        }
+        let lowres_size = header
+            .lowres_image_format
+            .frame_size(lowres_width, lowres_height)?;
+        if let Some(resource) = header
+            .resources
+            .get_by_type_mut(ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE)
+        {
+            resource.data = offset;
+        }
+        if let Some(resource) = header
+            .resources
+            .get_by_type_mut(ResourceType::VTF_LEGACY_RSRC_IMAGE)
+        {
+            resource.data = offset + lowres_size;
+        }
+
+        let mut header_data = Vec::with_capacity(header.size());
+        header.write(&mut header_data)?;
+        assert!(header_data.len() <= header.size(), "invalid header size");
+        header_data.resize(header.size(), 0);
+        writer.write_all(&header_data)?;
+
+        for (_, chunk) in &chunks {
+            writer.write_all(&(chunk.len() as u32).to_le_bytes())?;
+            writer.write_all(chunk)?;
+        }
+
+        if self.thumbnail {
+            let thumbnail =
+                frames[0].resize_exact(lowres_width, lowres_height, FilterType::Triangle);
+            writer.write_all(&encode(
+                &thumbnail,
+                ImageFormat::Dxt1,
+                &self.encode_options,
+            )?)?;
+        }
+
+        #[cfg(feature = "rayon")]
+        let mipmaps = frames.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let mipmaps = frames.iter();
+        let mipmaps = mipmaps
+            .map(|frame| generate_mipmaps(frame, mipmap_count, &mipmap_options))
+            .collect::<Vec<_>>();
+
+        // mipmaps are stored from smallest to largest, with all frames for each mip level
+        let images = (0..mipmap_count as usize)
+            .rev()
+            .flat_map(|level| mipmaps.iter().map(move |frame| &frame[level]))
+            .collect::<Vec<_>>();
+
+        #[cfg(feature = "rayon")]
+        let images = images.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let images = images.iter();
+        let encoded = images
+            .map(|image| match one_bit_cutoff {
+                Some(cutoff) => encode(
+                    &threshold_alpha(image, cutoff),
+                    format,
+                    &self.encode_options,
+                ),
+                None => encode(image, format, &self.encode_options),
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        for data in encoded {
+            writer.write_all(&data)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn address_mode_for_flag(flags: TextureFlags, clamp_flag: TextureFlags) -> AddressMode {
+    if flags.contains(clamp_flag) {
+        AddressMode::Clamp
+    } else {
+        AddressMode::Wrap
+    }
+}
+
+/// Make pixels with alpha above `cutoff` fully opaque and all others fully transparent
+fn threshold_alpha(image: &DynamicImage, cutoff: f32) -> DynamicImage {
+    let mut image = image.to_rgba8();
+    for pixel in image.pixels_mut() {
+        pixel[3] = if pixel[3] as f32 / 255.0 > cutoff {
+            255
+        } else {
+            0
+        };
+    }
+    DynamicImage::ImageRgba8(image)
+}
+
+/// Size of the thumbnail for an image, the largest mip level that fits in 16x16
+fn thumbnail_dimensions(width: u32, height: u32) -> (u32, u32) {
+    let mut level = 0;
+    loop {
+        let (mip_width, mip_height) = mip_dimensions(width, height, level);
+        if mip_width <= VTFBuilder::THUMBNAIL_SIZE && mip_height <= VTFBuilder::THUMBNAIL_SIZE {
+            return (mip_width, mip_height);
+        }
+        level += 1;
+    }
+}
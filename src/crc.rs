@@ -0,0 +1,27 @@
+/// Lookup table for the reflected IEEE polynomial
+const TABLE: [u32; 256] = {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut value = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            value = if value & 1 == 1 {
+                (value >> 1) ^ 0xedb8_8320
+            } else {
+                value >> 1
+            };
+            bit += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE) checksum, as stored in the CRC resource
+pub fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        (crc >> 8) ^ TABLE[((crc ^ *byte as u32) & 0xff) as usize]
+    })
+}
@@ -0,0 +1,282 @@
+use crate::color::is_linear;
+use crate::flags::TextureFlags;
+use crate::image::ImageFormat;
+use crate::Error;
+use half::f16;
+use image::{DynamicImage, Rgba};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::vec::Vec;
+use texpresso::{Algorithm, Format, Params, COLOUR_WEIGHTS_PERCEPTUAL, COLOUR_WEIGHTS_UNIFORM};
+
+/// How single channel formats (`I8`, `Ia88`) derive their intensity from rgb input
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Luminance {
+    /// Weighted with the ITU-R BT.601 coefficients, as used by vtex
+    #[default]
+    Rec601,
+    /// Weighted with the ITU-R BT.709 coefficients
+    Rec709,
+    /// Take the red channel as-is
+    Red,
+    /// Take the green channel as-is
+    Green,
+    /// Take the blue channel as-is
+    Blue,
+}
+
+impl Luminance {
+    fn weights(&self) -> [f32; 3] {
+        match self {
+            Luminance::Rec601 => [0.299, 0.587, 0.114],
+            Luminance::Rec709 => [0.2126, 0.7152, 0.0722],
+            Luminance::Red => [1.0, 0.0, 0.0],
+            Luminance::Green => [0.0, 1.0, 0.0],
+            Luminance::Blue => [0.0, 0.0, 1.0],
+        }
+    }
+
+    pub fn apply(&self, pixel: Rgba<u8>) -> u8 {
+        let [r, g, b] = self.weights();
+        let value = pixel[0] as f32 * r + pixel[1] as f32 * g + pixel[2] as f32 * b;
+        value.round().clamp(0.0, 255.0) as u8
+    }
+}
+
+/// Endpoint fitting algorithm used for DXT compression, from fastest to best quality
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum DxtAlgorithm {
+    RangeFit,
+    #[default]
+    ClusterFit,
+    IterativeClusterFit,
+}
+
+/// Quality settings for DXT compression
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DxtQuality {
+    pub algorithm: DxtAlgorithm,
+    /// Weigh the color channels by their perceived brightness instead of treating them equally
+    pub perceptual: bool,
+    /// Weigh the color error of every pixel by its alpha, so transparent areas get less precision
+    pub weigh_by_alpha: bool,
+}
+
+impl DxtQuality {
+    /// Fast compression for previews
+    pub const FAST: DxtQuality = DxtQuality {
+        algorithm: DxtAlgorithm::RangeFit,
+        perceptual: true,
+        weigh_by_alpha: false,
+    };
+
+    /// Slow compression for the best results
+    pub const HIGH: DxtQuality = DxtQuality {
+        algorithm: DxtAlgorithm::IterativeClusterFit,
+        perceptual: true,
+        weigh_by_alpha: true,
+    };
+
+    fn params(&self) -> Params {
+        Params {
+            algorithm: match self.algorithm {
+                DxtAlgorithm::RangeFit => Algorithm::RangeFit,
+                DxtAlgorithm::ClusterFit => Algorithm::ClusterFit,
+                DxtAlgorithm::IterativeClusterFit => Algorithm::IterativeClusterFit,
+            },
+            weights: if self.perceptual {
+                COLOUR_WEIGHTS_PERCEPTUAL
+            } else {
+                COLOUR_WEIGHTS_UNIFORM
+            },
+            weigh_colour_by_alpha: self.weigh_by_alpha,
+        }
+    }
+}
+
+impl Default for DxtQuality {
+    fn default() -> Self {
+        DxtQuality {
+            algorithm: DxtAlgorithm::ClusterFit,
+            perceptual: true,
+            weigh_by_alpha: false,
+        }
+    }
+}
+
+/// Settings that control how image data is converted into the target format
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EncodeOptions {
+    pub luminance: Luminance,
+    pub dxt_quality: DxtQuality,
+}
+
+/// What the alpha channel of an image is used for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlphaContent {
+    /// Every pixel is fully opaque
+    Opaque,
+    /// Every pixel is either fully opaque or fully transparent
+    OneBit,
+    /// The alpha channel contains intermediate values
+    EightBit,
+}
+
+impl AlphaContent {
+    pub fn of(frames: &[DynamicImage]) -> Self {
+        let mut content = AlphaContent::Opaque;
+        for frame in frames.iter().filter(|frame| frame.color().has_alpha()) {
+            for pixel in frame.to_rgba8().pixels() {
+                match pixel[3] {
+                    255 => {}
+                    0 => content = AlphaContent::OneBit,
+                    _ => return AlphaContent::EightBit,
+                }
+            }
+        }
+        content
+    }
+
+    /// The `ONEBITALPHA` or `EIGHTBITALPHA` header flag matching the content
+    pub fn flags(&self) -> TextureFlags {
+        match self {
+            AlphaContent::Opaque => TextureFlags::empty(),
+            AlphaContent::OneBit => TextureFlags::ONEBITALPHA,
+            AlphaContent::EightBit => TextureFlags::EIGHTBITALPHA,
+        }
+    }
+}
+
+/// Settings for picking the output format based on the image content
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AutoFormatOptions {
+    /// Use uncompressed formats instead of DXT, trading file size for quality
+    pub prefer_uncompressed: bool,
+}
+
+/// Pick the output format that fits the content of all frames
+///
+/// Float images are stored as `RGBA16161616F`, grayscale images as `I8` or `IA88` and
+/// everything else as DXT1, DXT1 with one bit alpha or DXT5 depending on the alpha channel.
+pub fn select_format(frames: &[DynamicImage], options: &AutoFormatOptions) -> ImageFormat {
+    if frames.iter().any(is_linear) {
+        return ImageFormat::Rgba16161616f;
+    }
+
+    let alpha = AlphaContent::of(frames);
+    let grayscale = frames.iter().all(|frame| {
+        frame
+            .to_rgba8()
+            .pixels()
+            .all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2])
+    });
+
+    match (grayscale, alpha, options.prefer_uncompressed) {
+        (true, AlphaContent::Opaque, _) => ImageFormat::I8,
+        (true, _, _) => ImageFormat::Ia88,
+        (false, AlphaContent::Opaque, false) => ImageFormat::Dxt1,
+        (false, AlphaContent::OneBit, false) => ImageFormat::Dxt1Onebitalpha,
+        (false, AlphaContent::EightBit, false) => ImageFormat::Dxt5,
+        (false, AlphaContent::Opaque, true) => ImageFormat::Rgb888,
+        (false, _, true) => ImageFormat::Rgba8888,
+    }
+}
+
+/// Encode a single image into the raw data for `format`
+pub fn encode(
+    image: &DynamicImage,
+    format: ImageFormat,
+    options: &EncodeOptions,
+) -> Result<Vec<u8>, Error> {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+
+    match format {
+        ImageFormat::Dxt1 => Ok(compress_dxt(image, Format::Bc1, &options.dxt_quality)),
+        ImageFormat::Dxt1Onebitalpha => Ok(compress_dxt(image, Format::Bc1, &options.dxt_quality)),
+        ImageFormat::Dxt3 => Ok(compress_dxt(image, Format::Bc2, &options.dxt_quality)),
+        ImageFormat::Dxt5 => Ok(compress_dxt(image, Format::Bc3, &options.dxt_quality)),
+        ImageFormat::Rgba8888 => Ok(image.to_rgba8().into_raw()),
+        ImageFormat::Rgb888 => Ok(image.to_rgb8().into_raw()),
+        ImageFormat::Bgra8888 => Ok(image
+            .to_rgba8()
+            .pixels()
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0], pixel[3]])
+            .collect()),
+        ImageFormat::Bgr888 => Ok(image
+            .to_rgb8()
+            .pixels()
+            .flat_map(|pixel| [pixel[2], pixel[1], pixel[0]])
+            .collect()),
+        ImageFormat::I8 => {
+            let image_data = image.to_rgba8();
+            Ok(image_data
+                .pixels()
+                .map(|pixel| options.luminance.apply(*pixel))
+                .collect())
+        }
+        ImageFormat::Ia88 => {
+            let image_data = image.to_rgba8();
+            let mut data = Vec::with_capacity(width * height * 2);
+            for pixel in image_data.pixels() {
+                data.push(options.luminance.apply(*pixel));
+                data.push(pixel[3]);
+            }
+            Ok(data)
+        }
+        ImageFormat::A8 => {
+            let image_data = image.to_rgba8();
+            Ok(image_data.pixels().map(|pixel| pixel[3]).collect())
+        }
+        ImageFormat::Rgba16161616 => Ok(image
+            .to_rgba16()
+            .into_raw()
+            .into_iter()
+            .flat_map(u16::to_le_bytes)
+            .collect()),
+        ImageFormat::Rgba16161616f => Ok(image
+            .to_rgba32f()
+            .into_raw()
+            .into_iter()
+            .flat_map(|value| f16::from_f32(value).to_le_bytes())
+            .collect()),
+        ImageFormat::R32f => Ok(image
+            .to_rgba32f()
+            .pixels()
+            .flat_map(|pixel| pixel[0].to_le_bytes())
+            .collect()),
+        ImageFormat::Rgb323232f => Ok(float_bytes(image.to_rgb32f().into_raw())),
+        ImageFormat::Rgba32323232f => Ok(float_bytes(image.to_rgba32f().into_raw())),
+        _ => Err(Error::UnsupportedEncodeImageFormat(format)),
+    }
+}
+
+fn float_bytes(values: Vec<f32>) -> Vec<u8> {
+    values.into_iter().flat_map(f32::to_le_bytes).collect()
+}
+
+fn compress_dxt(image: &DynamicImage, variant: Format, quality: &DxtQuality) -> Vec<u8> {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    let image_data = image.to_rgba8();
+    let mut data = vec![0; variant.compressed_size(width, height)];
+
+    // every row of blocks is compressed independently so they can be spread over threads
+    let block_row_size = variant.compressed_size(width, 4);
+    let pixel_row_size = width * 4 * 4;
+    let params = quality.params();
+
+    #[cfg(feature = "rayon")]
+    let rows = data
+        .par_chunks_mut(block_row_size)
+        .zip(image_data.as_raw().par_chunks(pixel_row_size));
+    #[cfg(not(feature = "rayon"))]
+    let rows = data
+        .chunks_mut(block_row_size)
+        .zip(image_data.as_raw().chunks(pixel_row_size));
+
+    rows.for_each(|(output, input)| {
+        variant.compress(input, width, input.len() / (width * 4), params, output);
+    });
+    data
+}
@@ -0,0 +1,73 @@
+use bitflags::bitflags;
+use std::fmt::{self, Debug, Display, Formatter};
+
+bitflags! {
+    /// Texture flags stored in the vtf header
+    ///
+    /// Unknown bits are kept when reading and writing.
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct TextureFlags: u32 {
+        const POINTSAMPLE = 0x0000_0001;
+        const TRILINEAR = 0x0000_0002;
+        const CLAMPS = 0x0000_0004;
+        const CLAMPT = 0x0000_0008;
+        const ANISOTROPIC = 0x0000_0010;
+        const HINT_DXT5 = 0x0000_0020;
+        const SRGB = 0x0000_0040;
+        const NORMAL = 0x0000_0080;
+        const NOMIP = 0x0000_0100;
+        const NOLOD = 0x0000_0200;
+        const ALL_MIPS = 0x0000_0400;
+        const PROCEDURAL = 0x0000_0800;
+        const ONEBITALPHA = 0x0000_1000;
+        const EIGHTBITALPHA = 0x0000_2000;
+        const ENVMAP = 0x0000_4000;
+        const RENDERTARGET = 0x0000_8000;
+        const DEPTHRENDERTARGET = 0x0001_0000;
+        const NODEBUGOVERRIDE = 0x0002_0000;
+        const SINGLECOPY = 0x0004_0000;
+        const PRE_SRGB = 0x0008_0000;
+        const NODEPTHBUFFER = 0x0080_0000;
+        const CLAMPU = 0x0200_0000;
+        const VERTEXTEXTURE = 0x0400_0000;
+        const SSBUMP = 0x0800_0000;
+        const BORDER = 0x2000_0000;
+
+        const _ = !0;
+    }
+}
+
+bitflags! {
+    /// Extended texture flags stored in the `TSO` resource
+    ///
+    /// No bits have a documented meaning, all of them are kept as-is.
+    #[derive(Clone, Copy, PartialEq, Eq, Hash, Default)]
+    #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    pub struct ExtendedFlags: u32 {
+        const _ = !0;
+    }
+}
+
+macro_rules! impl_flags_fmt {
+    ($flags:ty) => {
+        impl Display for $flags {
+            /// Lists the set flags separated by `|`, unknown bits are shown in hex
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                if self.is_empty() {
+                    return f.write_str("0x0");
+                }
+                bitflags::parser::to_writer(self, f)
+            }
+        }
+
+        impl Debug for $flags {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                Display::fmt(self, f)
+            }
+        }
+    };
+}
+
+impl_flags_fmt!(TextureFlags);
+impl_flags_fmt!(ExtendedFlags);
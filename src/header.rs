@@ -1,18 +1,34 @@
+use crate::crc::crc32;
+use crate::flags::{ExtendedFlags, TextureFlags};
 use crate::image::ImageFormat;
-use crate::resources::ResourceList;
+use crate::mipmap::mipmap_count;
+use crate::resources::{LodControl, ResourceId, ResourceList, ResourceType};
 use crate::Error;
 use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::convert::TryFrom;
+use std::fmt::{self, Debug, Formatter};
 use std::io::{Read, Write};
 
-#[derive(Debug, Clone)]
+/// How strictly headers are validated when reading
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ReadMode {
+    /// Reject anything that doesn't match the format
+    Strict,
+    /// Only reject headers that would lead to reading invalid data, a mismatched header size is
+    /// accepted since other tools don't always write it correctly
+    #[default]
+    Lenient,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[derive(Clone)]
 pub struct VTFHeader {
     pub signature: u32,
     pub version: [u32; 2],
     pub header_size: u32,
     pub width: u16,
     pub height: u16,
-    pub flags: u32,
+    pub flags: TextureFlags,
     pub frames: u16,
     pub first_frame: u16,
     pub reflectivity: [f32; 3],
@@ -43,7 +59,7 @@ impl VTFHeader {
         let header_size = bytes.read_u32::<LittleEndian>()?;
         let width = bytes.read_u16::<LittleEndian>()?;
         let height = bytes.read_u16::<LittleEndian>()?;
-        let flags = bytes.read_u32::<LittleEndian>()?;
+        let flags = TextureFlags::from_bits_retain(bytes.read_u32::<LittleEndian>()?);
         let frames = bytes.read_u16::<LittleEndian>()?;
         let first_frame = bytes.read_u16::<LittleEndian>()?;
 
@@ -107,7 +123,7 @@ impl VTFHeader {
         bytes.write_u32::<LittleEndian>(self.size() as u32)?;
         bytes.write_u16::<LittleEndian>(self.width)?;
         bytes.write_u16::<LittleEndian>(self.height)?;
-        bytes.write_u32::<LittleEndian>(self.flags)?;
+        bytes.write_u32::<LittleEndian>(self.flags.bits())?;
         bytes.write_u16::<LittleEndian>(self.frames)?;
         bytes.write_u16::<LittleEndian>(self.first_frame)?;
 
@@ -142,6 +158,149 @@ impl VTFHeader {
         Ok(())
     }
 
+    /// Check that the header describes a readable file of `file_size` bytes
+    pub fn validate(&self, file_size: usize, mode: ReadMode) -> Result<(), Error> {
+        if self.width == 0 || self.height == 0 {
+            return Err(Error::InvalidDimensions(self.width, self.height));
+        }
+        if self.depth == 0 {
+            return Err(Error::InvalidDepth(self.depth));
+        }
+        if self.frames == 0 {
+            return Err(Error::InvalidFrameCount(self.frames));
+        }
+
+        let max_mipmaps = mipmap_count(self.width.max(self.height) as u32, self.depth as u32);
+        if self.mipmap_count == 0 || self.mipmap_count > max_mipmaps {
+            return Err(Error::InvalidMipmapCount(self.mipmap_count, max_mipmaps));
+        }
+
+        if mode == ReadMode::Strict && self.header_size != self.size() as u32 {
+            return Err(Error::InvalidHeaderSize(
+                self.header_size,
+                self.size() as u32,
+            ));
+        }
+
+        for resource in &self.resources.resources {
+            // resources without a data chunk store their value inline
+            if resource.ty.has_data_chunk() && resource.data as usize > file_size {
+                return Err(Error::ResourceOutOfBounds(resource.ty, resource.data));
+            }
+        }
+
+        // the size of formats we can't decode is unknown, so their data can't be checked
+        match self.image_ends() {
+            Ok(Some((lowres_end, highres_end))) => {
+                for end in [lowres_end, highres_end] {
+                    if end > file_size as u64 {
+                        return Err(Error::ImageDataOutOfBounds(end, file_size));
+                    }
+                }
+            }
+            Ok(None) => return Err(Error::ImageDataOutOfBounds(u64::MAX, file_size)),
+            Err(_) => {}
+        }
+
+        Ok(())
+    }
+
+    /// Offsets just past the end of the low and high resolution image data
+    ///
+    /// Sizes are computed in `u64` since a header can describe more data than fits in a `u32`,
+    /// `None` if even that overflows.
+    fn image_ends(&self) -> Result<Option<(u64, u64)>, Error> {
+        let (lowres_offset, highres_offset) = self.image_offsets()?;
+        let lowres_size = self.lowres_image_format.data_size(
+            self.lowres_image_width as u64,
+            self.lowres_image_height as u64,
+        )?;
+
+        let mut highres_end = Some(highres_offset as u64);
+        for level in 0..self.mipmap_count as u32 {
+            let width = (self.width as u64 >> level).max(1);
+            let height = (self.height as u64 >> level).max(1);
+            let depth = (self.depth as u64 >> level).max(1);
+            let size = self
+                .highres_image_format
+                .data_size(width, height)?
+                .and_then(|size| size.checked_mul(depth))
+                .and_then(|size| size.checked_mul(self.frames as u64))
+                .and_then(|size| size.checked_mul(self.faces() as u64));
+            highres_end = highres_end
+                .zip(size)
+                .and_then(|(end, size)| end.checked_add(size));
+        }
+        Ok(lowres_size
+            .map(|size| lowres_offset as u64 + size)
+            .zip(highres_end))
+    }
+
+    /// Offsets of the low and high resolution image data
+    pub fn image_offsets(&self) -> Result<(u32, u32), Error> {
+        let lowres_offset = match self
+            .resources
+            .get_by_type(ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE)
+        {
+            Some(resource) => resource.data,
+            None => self.header_size,
+        };
+
+        let highres_offset = match self
+            .resources
+            .get_by_type(ResourceType::VTF_LEGACY_RSRC_IMAGE)
+        {
+            Some(resource) => resource.data,
+            None => lowres_offset.saturating_add(self.lowres_image_format.frame_size(
+                self.lowres_image_width as u32,
+                self.lowres_image_height as u32,
+            )?),
+        };
+
+        Ok((lowres_offset, highres_offset))
+    }
+
+    /// Checksum of the source image from the CRC resource
+    pub fn crc(&self) -> Option<u32> {
+        self.resources
+            .get_by_id(ResourceId::Crc)
+            .map(|resource| resource.data)
+    }
+
+    /// Check the source image against the CRC resource, `None` if the file has no CRC
+    pub fn verify_crc(&self, source: &[u8]) -> Option<bool> {
+        self.crc().map(|crc| crc == crc32(source))
+    }
+
+    pub fn lod_control(&self) -> Option<LodControl> {
+        self.resources
+            .get_by_id(ResourceId::LodControl)
+            .map(|resource| resource.data.into())
+    }
+
+    /// Additional flags from the extended flags resource
+    pub fn extended_flags(&self) -> Option<ExtendedFlags> {
+        self.resources
+            .get_by_id(ResourceId::ExtendedFlags)
+            .map(|resource| ExtendedFlags::from_bits_retain(resource.data))
+    }
+
+    /// Number of faces stored for every frame, 6 or 7 for environment maps depending on whether
+    /// the spheremap face is included
+    pub fn faces(&self) -> u32 {
+        Self::face_count(self.version, self.flags, self.first_frame)
+    }
+
+    pub(crate) fn face_count(version: [u32; 2], flags: TextureFlags, first_frame: u16) -> u32 {
+        if !flags.contains(TextureFlags::ENVMAP) {
+            1
+        } else if version[1] < 5 && first_frame != 0xffff {
+            7
+        } else {
+            6
+        }
+    }
+
     pub fn size(&self) -> usize {
         match self.version[1] {
             0 | 1 => 64,
@@ -149,3 +308,28 @@ impl VTFHeader {
         }
     }
 }
+
+impl Debug for VTFHeader {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VTFHeader")
+            .field("signature", &self.signature)
+            .field("version", &self.version)
+            .field("header_size", &self.header_size)
+            .field("width", &self.width)
+            .field("height", &self.height)
+            .field("flags", &self.flags)
+            .field("extended_flags", &self.extended_flags())
+            .field("frames", &self.frames)
+            .field("first_frame", &self.first_frame)
+            .field("reflectivity", &self.reflectivity)
+            .field("bumpmap_scale", &self.bumpmap_scale)
+            .field("highres_image_format", &self.highres_image_format)
+            .field("mipmap_count", &self.mipmap_count)
+            .field("lowres_image_format", &self.lowres_image_format)
+            .field("lowres_image_width", &self.lowres_image_width)
+            .field("lowres_image_height", &self.lowres_image_height)
+            .field("depth", &self.depth)
+            .field("resources", &self.resources)
+            .finish()
+    }
+}
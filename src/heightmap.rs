@@ -0,0 +1,174 @@
+use crate::mipmap::AddressMode;
+use image::{DynamicImage, ImageBuffer, Luma, Rgb, Rgba};
+
+/// Kernel used to estimate the slope of the height map
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum GradientKernel {
+    /// Difference between the two direct neighbours, the sharpest result
+    CentralDifference,
+    /// 3x3 kernel with equal weights for the neighbouring rows
+    Prewitt,
+    /// 3x3 kernel with the center row weighted double
+    #[default]
+    Sobel,
+    /// 3x3 kernel with better rotational symmetry
+    Scharr,
+}
+
+impl GradientKernel {
+    /// Weights for the neighbouring rows and the center row, normalized to a sum of 1
+    fn weights(&self) -> Option<(f32, f32)> {
+        match self {
+            GradientKernel::CentralDifference => None,
+            GradientKernel::Prewitt => Some((1.0 / 3.0, 1.0 / 3.0)),
+            GradientKernel::Sobel => Some((0.25, 0.5)),
+            GradientKernel::Scharr => Some((3.0 / 16.0, 10.0 / 16.0)),
+        }
+    }
+}
+
+/// Settings for converting a height map
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HeightMapOptions {
+    /// Height in pixels of a fully white texel, higher values give stronger bumps
+    pub scale: f32,
+    pub kernel: GradientKernel,
+    /// Edge handling when sampling the height map, tiling textures should wrap
+    pub address_mode: AddressMode,
+    /// How many pixels to trace towards each light direction when computing self shadowing
+    /// for ssbumps
+    pub shadow_distance: u32,
+}
+
+impl Default for HeightMapOptions {
+    fn default() -> Self {
+        HeightMapOptions {
+            scale: 4.0,
+            kernel: GradientKernel::default(),
+            address_mode: AddressMode::Wrap,
+            shadow_distance: 16,
+        }
+    }
+}
+
+/// The three directions of Source's radiosity normal mapping basis, used by ssbumps
+const BUMP_BASIS: [[f32; 3]; 3] = [
+    [0.816_496_6, 0.0, 0.577_350_26],
+    [-0.408_248_3, 0.707_106_77, 0.577_350_26],
+    [-0.408_248_3, -0.707_106_77, 0.577_350_26],
+];
+
+struct HeightMap {
+    heights: ImageBuffer<Luma<f32>, Vec<f32>>,
+    options: HeightMapOptions,
+}
+
+impl HeightMap {
+    fn new(image: &DynamicImage, options: &HeightMapOptions) -> Self {
+        HeightMap {
+            heights: image.to_luma32f(),
+            options: *options,
+        }
+    }
+
+    /// Height in pixels at a position, which can be outside of the image
+    fn height(&self, x: i64, y: i64) -> f32 {
+        let (width, height) = (self.heights.width() as i64, self.heights.height() as i64);
+        let (x, y) = match self.options.address_mode {
+            AddressMode::Clamp => (x.clamp(0, width - 1), y.clamp(0, height - 1)),
+            AddressMode::Wrap => (x.rem_euclid(width), y.rem_euclid(height)),
+        };
+        self.heights.get_pixel(x as u32, y as u32)[0] * self.options.scale
+    }
+
+    /// Unit normal at a pixel, with the y axis pointing down like the image rows
+    fn normal(&self, x: i64, y: i64) -> [f32; 3] {
+        let (dx, dy) = match self.options.kernel.weights() {
+            None => (
+                (self.height(x + 1, y) - self.height(x - 1, y)) / 2.0,
+                (self.height(x, y + 1) - self.height(x, y - 1)) / 2.0,
+            ),
+            Some((side, center)) => {
+                let mut dx = 0.0;
+                let mut dy = 0.0;
+                for (offset, weight) in [(-1, side), (0, center), (1, side)] {
+                    dx +=
+                        (self.height(x + 1, y + offset) - self.height(x - 1, y + offset)) * weight;
+                    dy +=
+                        (self.height(x + offset, y + 1) - self.height(x + offset, y - 1)) * weight;
+                }
+                (dx / 2.0, dy / 2.0)
+            }
+        };
+
+        let length = (dx * dx + dy * dy + 1.0).sqrt();
+        [-dx / length, -dy / length, 1.0 / length]
+    }
+
+    /// How much light from a direction reaches a pixel, by tracing over the height map
+    fn visibility(&self, x: i64, y: i64, direction: [f32; 3]) -> f32 {
+        let horizontal = (direction[0] * direction[0] + direction[1] * direction[1]).sqrt();
+        let (step_x, step_y) = (direction[0] / horizontal, -direction[1] / horizontal);
+        let slope = direction[2] / horizontal;
+        let start = self.height(x, y);
+
+        let mut occlusion: f32 = 0.0;
+        for distance in 1..=self.options.shadow_distance {
+            let distance = distance as f32;
+            let sample = self.height(
+                x + (step_x * distance).round() as i64,
+                y + (step_y * distance).round() as i64,
+            );
+            let blocked = (sample - start - slope * distance) / distance;
+            occlusion = occlusion.max(blocked);
+        }
+
+        1.0 - occlusion.clamp(0.0, 1.0)
+    }
+}
+
+/// Convert a grayscale height map into a tangent space normal map
+///
+/// The normals use the DirectX convention Source expects, with green pointing down.
+pub fn height_to_normal(image: &DynamicImage, options: &HeightMapOptions) -> DynamicImage {
+    let map = HeightMap::new(image, options);
+    DynamicImage::ImageRgba8(ImageBuffer::from_fn(
+        image.width(),
+        image.height(),
+        |x, y| {
+            let normal = map.normal(x as i64, y as i64);
+            Rgba([
+                encode_unit(normal[0]),
+                encode_unit(normal[1]),
+                encode_unit(normal[2]),
+                255,
+            ])
+        },
+    ))
+}
+
+/// Convert a grayscale height map into a self shadowing bump map
+///
+/// Every channel stores the lighting from one of the directions of the bump basis, including
+/// the shadows cast by the surrounding height map.
+pub fn height_to_ssbump(image: &DynamicImage, options: &HeightMapOptions) -> DynamicImage {
+    let map = HeightMap::new(image, options);
+    DynamicImage::ImageRgb8(ImageBuffer::from_fn(
+        image.width(),
+        image.height(),
+        |x, y| {
+            let (x, y) = (x as i64, y as i64);
+            let normal = map.normal(x, y);
+            Rgb(BUMP_BASIS.map(|basis| {
+                let light = normal[0] * basis[0] - normal[1] * basis[1] + normal[2] * basis[2];
+                let value = light.max(0.0) * map.visibility(x, y, basis);
+                (value.clamp(0.0, 1.0) * 255.0).round() as u8
+            }))
+        },
+    ))
+}
+
+/// Map a value in the -1..1 range to a byte
+fn encode_unit(value: f32) -> u8 {
+    ((value * 0.5 + 0.5).clamp(0.0, 1.0) * 255.0).round() as u8
+}
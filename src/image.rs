@@ -1,10 +1,12 @@
 use crate::header::VTFHeader;
 use crate::utils::get_offset;
 use crate::Error;
+use half::f16;
 use image::{DynamicImage, ImageBuffer, Pixel};
 use num_enum::TryFromPrimitive;
 use parse_display::Display;
-use std::ops::Deref;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
 use std::vec::Vec;
 use texpresso::Format;
 
@@ -47,26 +49,40 @@ impl<'a> VTFImage<'a> {
     }
 
     fn decode_dxt(&self, bytes: &[u8], variant: Format) -> Result<Vec<u8>, Error> {
-        let mut output: Vec<u8> = vec![0; self.width as usize * self.height as usize * 4];
-        variant.decompress(
-            bytes,
-            self.width as usize,
-            self.height as usize,
-            &mut output,
-        );
+        let width = self.width as usize;
+        let mut output: Vec<u8> = vec![0; width * self.height as usize * 4];
+        if output.is_empty() {
+            return Ok(output);
+        }
+
+        // every row of blocks is decompressed independently so they can be spread over threads
+        let block_row_size = variant.compressed_size(width, 4);
+        let pixel_row_size = width * 4 * 4;
+
+        #[cfg(feature = "rayon")]
+        let rows = output
+            .par_chunks_mut(pixel_row_size)
+            .zip(bytes.par_chunks(block_row_size));
+        #[cfg(not(feature = "rayon"))]
+        let rows = output
+            .chunks_mut(pixel_row_size)
+            .zip(bytes.chunks(block_row_size));
+
+        rows.for_each(|(output, input)| {
+            variant.decompress(input, width, output.len() / (width * 4), output);
+        });
         Ok(output)
     }
 
-    fn image_from_buffer<P, Container, F>(
+    fn image_from_buffer<P, F>(
         &self,
-        buffer: Container,
+        buffer: Vec<P::Subpixel>,
         format: F,
     ) -> Result<DynamicImage, Error>
     where
         P: Pixel + 'static,
         P::Subpixel: 'static,
-        Container: Deref<Target = [P::Subpixel]>,
-        F: FnOnce(ImageBuffer<P, Container>) -> DynamicImage,
+        F: FnOnce(ImageBuffer<P, Vec<P::Subpixel>>) -> DynamicImage,
     {
         ImageBuffer::from_raw(self.width as u32, self.height as u32, buffer)
             .map(format)
@@ -96,21 +112,59 @@ impl<'a> VTFImage<'a> {
                 self.image_from_buffer(bytes.to_vec(), DynamicImage::ImageRgba8)
             }
             ImageFormat::Rgb888 => self.image_from_buffer(bytes.to_vec(), DynamicImage::ImageRgb8),
+            ImageFormat::I8 => self.image_from_buffer(bytes.to_vec(), DynamicImage::ImageLuma8),
+            ImageFormat::Ia88 => self.image_from_buffer(bytes.to_vec(), DynamicImage::ImageLumaA8),
+            ImageFormat::A8 => {
+                let buf: Vec<u8> = bytes.iter().flat_map(|alpha| [0, *alpha]).collect();
+                self.image_from_buffer(buf, DynamicImage::ImageLumaA8)
+            }
             ImageFormat::Bgr888 => {
-                let mut bgra = bytes.to_vec();
-                convert_bgra(&mut bgra);
-                self.image_from_buffer(bgra, DynamicImage::ImageRgb8)
+                let mut bgr = bytes.to_vec();
+                for pixel in bgr.chunks_exact_mut(3) {
+                    pixel.swap(0, 2);
+                }
+                self.image_from_buffer(bgr, DynamicImage::ImageRgb8)
             }
             ImageFormat::Bgra8888 => {
                 let mut bgra = bytes.to_vec();
                 convert_bgra(&mut bgra);
-                self.image_from_buffer(bgra, DynamicImage::ImageRgb8)
+                self.image_from_buffer(bgra, DynamicImage::ImageRgba8)
+            }
+            ImageFormat::Rgba16161616 => {
+                let buf = bytes
+                    .chunks_exact(2)
+                    .map(|value| u16::from_le_bytes([value[0], value[1]]))
+                    .collect();
+                self.image_from_buffer(buf, DynamicImage::ImageRgba16)
+            }
+            ImageFormat::Rgba16161616f => {
+                let buf = bytes
+                    .chunks_exact(2)
+                    .map(|value| f16::from_le_bytes([value[0], value[1]]).to_f32())
+                    .collect();
+                self.image_from_buffer(buf, DynamicImage::ImageRgba32F)
+            }
+            ImageFormat::R32f => {
+                let buf = read_f32(bytes).flat_map(|red| [red, 0.0, 0.0]).collect();
+                self.image_from_buffer(buf, DynamicImage::ImageRgb32F)
+            }
+            ImageFormat::Rgb323232f => {
+                self.image_from_buffer(read_f32(bytes).collect(), DynamicImage::ImageRgb32F)
+            }
+            ImageFormat::Rgba32323232f => {
+                self.image_from_buffer(read_f32(bytes).collect(), DynamicImage::ImageRgba32F)
             }
             _ => Err(Error::UnsupportedImageFormat(self.format)),
         }
     }
 }
 
+fn read_f32(bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|value| f32::from_le_bytes([value[0], value[1], value[2], value[3]]))
+}
+
 // https://github.com/image-rs/image/pull/1482#issuecomment-1402362448
 fn convert_bgra(bgra: &mut [u8]) {
     for src in bgra.chunks_exact_mut(4) {
@@ -122,6 +176,7 @@ fn convert_bgra(bgra: &mut [u8]) {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Display, Clone, Copy, PartialEq, TryFromPrimitive)]
 #[repr(i16)]
 pub enum ImageFormat {
@@ -153,27 +208,51 @@ pub enum ImageFormat {
     Rgba16161616f,
     Rgba16161616,
     Uvlx8888,
+    R32f,
+    Rgb323232f,
+    Rgba32323232f,
 }
 
 impl ImageFormat {
     pub fn frame_size(&self, width: u32, height: u32) -> Result<u32, Error> {
+        let (block_size, block_bytes) = self.block_layout()?;
+        Ok(((width + block_size - 1) / block_size)
+            * ((height + block_size - 1) / block_size)
+            * block_bytes)
+    }
+
+    /// Size of an image in this format, `None` if it doesn't fit in a `u64`
+    pub(crate) fn data_size(&self, width: u64, height: u64) -> Result<Option<u64>, Error> {
+        let (block_size, block_bytes) = self.block_layout()?;
+        let (block_size, block_bytes) = (block_size as u64, block_bytes as u64);
+        Ok(((width + block_size - 1) / block_size)
+            .checked_mul((height + block_size - 1) / block_size)
+            .and_then(|blocks| blocks.checked_mul(block_bytes)))
+    }
+
+    /// Width and height of a block of pixels and the number of bytes it is stored in
+    fn block_layout(&self) -> Result<(u32, u32), Error> {
         match self {
-            ImageFormat::None => Ok(0),
-            ImageFormat::Rgba8888 => Ok(width * height * 4),
-            ImageFormat::Abgr8888 => Ok(width * height * 4),
-            ImageFormat::Rgb888 => Ok(width * height * 3),
-            ImageFormat::Bgr888 => Ok(width * height * 3),
-            ImageFormat::Rgb565 => Ok(width * height * 2),
-            ImageFormat::I8 => Ok(width * height),
-            ImageFormat::Ia88 => Ok(width * height * 2),
-            ImageFormat::A8 => Ok(width * height),
-            ImageFormat::Argb8888 => Ok(width * height * 4),
-            ImageFormat::Bgra8888 => Ok(width * height * 4),
-            ImageFormat::Dxt1 => Ok(((width + 3) / 4) * ((height + 3) / 4) * 8),
-            ImageFormat::Dxt3 => Ok(((width + 3) / 4) * ((height + 3) / 4) * 16),
-            ImageFormat::Dxt5 => Ok(((width + 3) / 4) * ((height + 3) / 4) * 16),
-            ImageFormat::Rgba16161616f => Ok(width * height * 8),
-            ImageFormat::Rgba16161616 => Ok(width * height * 8),
+            ImageFormat::None => Ok((1, 0)),
+            ImageFormat::Rgba8888 => Ok((1, 4)),
+            ImageFormat::Abgr8888 => Ok((1, 4)),
+            ImageFormat::Rgb888 => Ok((1, 3)),
+            ImageFormat::Bgr888 => Ok((1, 3)),
+            ImageFormat::Rgb565 => Ok((1, 2)),
+            ImageFormat::I8 => Ok((1, 1)),
+            ImageFormat::Ia88 => Ok((1, 2)),
+            ImageFormat::A8 => Ok((1, 1)),
+            ImageFormat::Argb8888 => Ok((1, 4)),
+            ImageFormat::Bgra8888 => Ok((1, 4)),
+            ImageFormat::Dxt1 => Ok((4, 8)),
+            ImageFormat::Dxt1Onebitalpha => Ok((4, 8)),
+            ImageFormat::Dxt3 => Ok((4, 16)),
+            ImageFormat::Dxt5 => Ok((4, 16)),
+            ImageFormat::Rgba16161616f => Ok((1, 8)),
+            ImageFormat::Rgba16161616 => Ok((1, 8)),
+            ImageFormat::R32f => Ok((1, 4)),
+            ImageFormat::Rgb323232f => Ok((1, 12)),
+            ImageFormat::Rgba32323232f => Ok((1, 16)),
             _ => Err(Error::UnsupportedImageFormat(*self)),
         }
     }
@@ -0,0 +1,259 @@
+use crate::Error;
+use std::fmt::{self, Display, Formatter};
+use std::iter::Peekable;
+use std::str::Chars;
+use std::vec::Vec;
+
+/// Value of a KeyValues entry, either a string or a nested section
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Section(KeyValues),
+}
+
+/// Tree of key value pairs in Valve's KeyValues text format
+///
+/// Keys are not unique and keep the order they appear in.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct KeyValues {
+    pub entries: Vec<(String, Value)>,
+}
+
+impl KeyValues {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        let mut tokenizer = Tokenizer {
+            chars: input.chars().peekable(),
+            line: 1,
+            peeked: None,
+        };
+        let keyvalues = KeyValues::parse_section(&mut tokenizer)?;
+        match tokenizer.next()? {
+            None => Ok(keyvalues),
+            Some(_) => Err(tokenizer.error("unexpected '}'")),
+        }
+    }
+
+    fn parse_section(tokenizer: &mut Tokenizer) -> Result<Self, Error> {
+        let mut entries = Vec::new();
+        loop {
+            let key = match tokenizer.peek()? {
+                None | Some(Token::Close) => return Ok(KeyValues { entries }),
+                Some(Token::Open) => return Err(tokenizer.error("expected a key")),
+                Some(Token::String(_)) => match tokenizer.next()? {
+                    Some(Token::String(key)) => key,
+                    _ => unreachable!(),
+                },
+            };
+
+            let value = match tokenizer.next()? {
+                Some(Token::String(value)) => Value::String(value),
+                Some(Token::Open) => {
+                    let section = KeyValues::parse_section(tokenizer)?;
+                    if tokenizer.next()? != Some(Token::Close) {
+                        return Err(tokenizer.error("expected '}'"));
+                    }
+                    Value::Section(section)
+                }
+                _ => return Err(tokenizer.error("expected a value")),
+            };
+            entries.push((key, value));
+        }
+    }
+
+    /// First value for a key, keys are matched case-insensitive
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        self.entries
+            .iter()
+            .find(|(entry_key, _)| entry_key.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value)
+    }
+
+    /// First string value for a key
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        match self.get(key) {
+            Some(Value::String(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// First section for a key
+    pub fn get_section(&self, key: &str) -> Option<&KeyValues> {
+        match self.get(key) {
+            Some(Value::Section(section)) => Some(section),
+            _ => None,
+        }
+    }
+}
+
+impl KeyValues {
+    /// Serialize into the text format, failing for keys or values containing a quote
+    ///
+    /// Quotes can't be represented since escape sequences aren't used.
+    pub fn serialize(&self) -> Result<String, Error> {
+        self.check_quotes()?;
+        Ok(self.to_string())
+    }
+
+    fn check_quotes(&self) -> Result<(), Error> {
+        for (key, value) in &self.entries {
+            if key.contains('"') {
+                return Err(Error::InvalidKeyValues(format!("quote in key {key}")));
+            }
+            match value {
+                Value::String(value) if value.contains('"') => {
+                    return Err(Error::InvalidKeyValues(format!(
+                        "quote in the value of {key}"
+                    )))
+                }
+                Value::String(_) => {}
+                Value::Section(section) => section.check_quotes()?,
+            }
+        }
+        Ok(())
+    }
+
+    fn write(&self, f: &mut Formatter<'_>, depth: usize) -> fmt::Result {
+        let indent = "\t".repeat(depth);
+        for (key, value) in &self.entries {
+            match value {
+                Value::String(value) => writeln!(f, "{indent}\"{key}\" \"{value}\"")?,
+                Value::Section(section) => {
+                    writeln!(f, "{indent}\"{key}\"")?;
+                    writeln!(f, "{indent}{{")?;
+                    section.write(f, depth + 1)?;
+                    writeln!(f, "{indent}}}")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Display for KeyValues {
+    /// Serialize into the text format, with every key and value quoted
+    ///
+    /// Quotes inside keys or values can't be represented since escape sequences aren't used,
+    /// [`serialize`](KeyValues::serialize) checks for them.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    String(String),
+    Open,
+    Close,
+}
+
+struct Tokenizer<'a> {
+    chars: Peekable<Chars<'a>>,
+    line: usize,
+    peeked: Option<Option<Token>>,
+}
+
+impl Tokenizer<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidKeyValues(format!("{} on line {}", message, self.line))
+    }
+
+    fn peek(&mut self) -> Result<Option<&Token>, Error> {
+        if self.peeked.is_none() {
+            let token = self.read_token()?;
+            self.peeked = Some(token);
+        }
+        Ok(self.peeked.as_ref().and_then(Option::as_ref))
+    }
+
+    fn next(&mut self) -> Result<Option<Token>, Error> {
+        match self.peeked.take() {
+            Some(token) => Ok(token),
+            None => self.read_token(),
+        }
+    }
+
+    fn read_token(&mut self) -> Result<Option<Token>, Error> {
+        loop {
+            self.skip_whitespace_and_comments();
+            match self.chars.peek() {
+                None => return Ok(None),
+                Some('{') => {
+                    self.chars.next();
+                    return Ok(Some(Token::Open));
+                }
+                Some('}') => {
+                    self.chars.next();
+                    return Ok(Some(Token::Close));
+                }
+                // platform conditionals like [$WIN32] are ignored
+                Some('[') => {
+                    for c in self.chars.by_ref() {
+                        if c == ']' {
+                            break;
+                        }
+                    }
+                }
+                Some('"') => {
+                    self.chars.next();
+                    return self.read_quoted().map(|value| Some(Token::String(value)));
+                }
+                Some(_) => return Ok(Some(Token::String(self.read_unquoted()))),
+            }
+        }
+    }
+
+    fn skip_whitespace_and_comments(&mut self) {
+        while let Some(&c) = self.chars.peek() {
+            if c == '\n' {
+                self.line += 1;
+                self.chars.next();
+            } else if c.is_whitespace() {
+                self.chars.next();
+            } else if c == '/' {
+                let mut lookahead = self.chars.clone();
+                lookahead.next();
+                if lookahead.peek() != Some(&'/') {
+                    return;
+                }
+                while let Some(&c) = self.chars.peek() {
+                    if c == '\n' {
+                        break;
+                    }
+                    self.chars.next();
+                }
+            } else {
+                return;
+            }
+        }
+    }
+
+    /// Read a quoted string, escape sequences are not processed just like Valve's default
+    /// parser, so windows paths survive unchanged
+    fn read_quoted(&mut self) -> Result<String, Error> {
+        let mut value = String::new();
+        loop {
+            match self.chars.next() {
+                None => return Err(self.error("unterminated string")),
+                Some('"') => return Ok(value),
+                Some(c) => {
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    value.push(c);
+                }
+            }
+        }
+    }
+
+    fn read_unquoted(&mut self) -> String {
+        let mut value = String::new();
+        while let Some(&c) = self.chars.peek() {
+            if c.is_whitespace() || c == '{' || c == '}' || c == '"' {
+                break;
+            }
+            value.push(c);
+            self.chars.next();
+        }
+        value
+    }
+}
@@ -1,10 +1,25 @@
+pub mod builder;
+mod color;
+pub mod crc;
+pub mod encode;
+pub mod flags;
 pub mod header;
+pub mod heightmap;
 pub mod image;
+pub mod keyvalues;
+pub mod mipmap;
+pub mod reflectivity;
+pub mod resize;
 pub mod resources;
+pub mod sheet;
 mod utils;
+pub mod vtex;
 pub mod vtf;
 
+pub use crate::builder::VTFBuilder;
+pub use crate::flags::TextureFlags;
 pub use crate::image::ImageFormat;
+use crate::resources::ResourceType;
 use crate::vtf::VTF;
 use ::image::DynamicImage;
 use num_enum::TryFromPrimitiveError;
@@ -28,6 +43,40 @@ pub enum Error {
     InvalidImageSize,
     #[error("Encoding {0} images is not supported")]
     UnsupportedEncodeImageFormat(ImageFormat),
+    #[error("Writing vtf version {0}.{1} is not supported")]
+    UnsupportedVersion(u32, u32),
+    #[error("All frames need to have the same size")]
+    MismatchedFrameSize,
+    #[error("Alpha coverage cutoff {0} needs to be between 0 and 1")]
+    InvalidAlphaCutoff(f32),
+    #[error("Environment maps need {1} faces for every frame, got {0} images")]
+    MismatchedFaceCount(usize, u32),
+    #[error("Invalid image size {0}x{1}")]
+    InvalidDimensions(u16, u16),
+    #[error("Invalid depth {0}")]
+    InvalidDepth(u16),
+    #[error("Invalid frame count {0}")]
+    InvalidFrameCount(u16),
+    #[error("Invalid mipmap count {0}, at most {1} mipmaps fit the image size")]
+    InvalidMipmapCount(u8, u8),
+    #[error("Header size {0} does not match the expected size {1}")]
+    InvalidHeaderSize(u32, u32),
+    #[error("Resource {0:?} at offset {1} is past the end of the file")]
+    ResourceOutOfBounds(ResourceType, u32),
+    #[error("Image data ends at offset {0}, past the end of the file at {1}")]
+    ImageDataOutOfBounds(u64, usize),
+    #[error("Invalid particle sheet data: {0}")]
+    InvalidSheet(String),
+    #[error("Vtf version {0}.{1} does not support resources")]
+    ResourcesNotSupported(u32, u32),
+    #[error("Vtf version {0}.{1} does not support volume textures")]
+    UnsupportedVolumeTexture(u32, u32),
+    #[error("Vtf version {0}.{1} can't store first frame {2} of an environment map")]
+    UnsupportedEnvmapFirstFrame(u32, u32, u16),
+    #[error("Invalid KeyValues data: {0}")]
+    InvalidKeyValues(String),
+    #[error("Invalid value {1:?} for compile parameter {0}")]
+    InvalidCompileParameter(String, String),
 }
 
 impl From<TryFromPrimitiveError<image::ImageFormat>> for Error {
@@ -36,7 +85,7 @@ impl From<TryFromPrimitiveError<image::ImageFormat>> for Error {
     }
 }
 
-pub fn from_bytes(bytes: &[u8]) -> Result<VTF, Error> {
+pub fn from_bytes(bytes: &[u8]) -> Result<VTF<'_>, Error> {
     VTF::read(bytes)
 }
 
@@ -0,0 +1,254 @@
+use crate::color::{is_linear, linear_to_srgb, srgb_to_linear};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageBuffer, Rgba32FImage};
+use std::vec::Vec;
+
+/// How the filter samples past the edges of the image
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AddressMode {
+    /// Repeat the edge pixels, for textures with the clamp flags
+    #[default]
+    Clamp,
+    /// Sample from the opposite edge, for tiling textures
+    Wrap,
+}
+
+/// Settings for generating mipmaps of tangent space normal maps
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct NormalMapOptions {
+    /// Store how much the filtered normals got shortened in the alpha channel as a gloss factor,
+    /// following Toksvig with the given specular exponent
+    ///
+    /// This keeps bumpy surfaces from looking overly shiny at a distance.
+    pub toksvig_power: Option<f32>,
+}
+
+/// Settings for generating the mipmap chain of a texture
+#[derive(Debug, Clone, Copy)]
+pub struct MipmapOptions {
+    /// Filter used when downscaling each level
+    pub filter: FilterType,
+    /// Rescale the alpha of every level so the fraction of pixels passing an alpha test with
+    /// this reference cutoff stays the same as in the full size image
+    ///
+    /// Without this alpha tested textures like foliage and fences thin out at a distance.
+    /// The cutoff needs to be between 0 and 1. When building DXT1 with one bit alpha, pixels
+    /// above the cutoff are stored opaque and all others transparent.
+    pub alpha_coverage: Option<f32>,
+    /// Filter the color channels in linear space instead of their sRGB encoding, alpha is
+    /// always filtered as-is
+    ///
+    /// Disabled when not set. When building through [`VTFBuilder`](crate::VTFBuilder) an unset
+    /// value follows [`VTFBuilder::gamma_correct`](crate::VTFBuilder::gamma_correct).
+    pub gamma_correct: Option<bool>,
+    /// Edge handling in the horizontal direction, clamped when not set
+    ///
+    /// When building through [`VTFBuilder`](crate::VTFBuilder) an unset value follows the
+    /// `CLAMPS` flag, [`VTFBuilder::address_mode`](crate::VTFBuilder::address_mode) takes
+    /// precedence over both.
+    pub address_u: Option<AddressMode>,
+    /// Edge handling in the vertical direction, clamped when not set
+    ///
+    /// When building through [`VTFBuilder`](crate::VTFBuilder) an unset value follows the
+    /// `CLAMPT` flag, [`VTFBuilder::address_mode`](crate::VTFBuilder::address_mode) takes
+    /// precedence over both.
+    pub address_v: Option<AddressMode>,
+    /// Treat the image as a normal map, renormalizing every filtered texel
+    ///
+    /// Color is never gamma corrected for normal maps.
+    pub normal_map: Option<NormalMapOptions>,
+}
+
+impl Default for MipmapOptions {
+    fn default() -> Self {
+        MipmapOptions {
+            filter: FilterType::Triangle,
+            alpha_coverage: None,
+            gamma_correct: None,
+            address_u: None,
+            address_v: None,
+            normal_map: None,
+        }
+    }
+}
+
+/// Number of mipmaps in a full chain down to 1x1 for the given size
+pub fn mipmap_count(width: u32, height: u32) -> u8 {
+    (32 - width.max(height).max(1).leading_zeros()) as u8
+}
+
+/// Size of mip level `level` for an image of the given size
+pub fn mip_dimensions(width: u32, height: u32, level: u8) -> (u32, u32) {
+    (
+        width.wrapping_shr(level as u32).max(1),
+        height.wrapping_shr(level as u32).max(1),
+    )
+}
+
+/// Generate `count` mip levels for `image`, starting with the full size image
+///
+/// Every level is downscaled from the one before it, the intermediate levels are kept
+/// in floating point to prevent accumulating rounding errors.
+pub fn generate_mipmaps(
+    image: &DynamicImage,
+    count: u8,
+    options: &MipmapOptions,
+) -> Vec<DynamicImage> {
+    let mut levels = Vec::with_capacity(count as usize);
+    levels.push(image.clone());
+
+    let gamma_correct =
+        options.gamma_correct == Some(true) && options.normal_map.is_none() && !is_linear(image);
+
+    let mut current = image.to_rgba32f();
+    if gamma_correct {
+        convert_color(&mut current, srgb_to_linear);
+    }
+    let target_coverage = options
+        .alpha_coverage
+        .map(|cutoff| alpha_coverage(&current, cutoff));
+
+    for level in 1..count {
+        let (width, height) = mip_dimensions(image.width(), image.height(), level);
+        current = downscale(&current, width, height, options);
+
+        // the next level is generated from the unscaled alpha and unnormalized normals,
+        // so the next level is filtered from the same data as this one
+        let mut mip = current.clone();
+        if let Some(normal_map) = options.normal_map {
+            normalize(&mut mip, &normal_map);
+        }
+        if let (Some(cutoff), Some(target)) = (options.alpha_coverage, target_coverage) {
+            scale_alpha_to_coverage(&mut mip, cutoff, target);
+        }
+        if gamma_correct {
+            convert_color(&mut mip, linear_to_srgb);
+        }
+        levels.push(DynamicImage::ImageRgba32F(mip));
+    }
+
+    levels
+}
+
+/// Renormalize the normals stored in the color channels after filtering
+fn normalize(image: &mut Rgba32FImage, options: &NormalMapOptions) {
+    for pixel in image.pixels_mut() {
+        let normal = [
+            pixel[0] * 2.0 - 1.0,
+            pixel[1] * 2.0 - 1.0,
+            pixel[2] * 2.0 - 1.0,
+        ];
+        let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+        if length <= f32::EPSILON {
+            continue;
+        }
+
+        for channel in 0..3 {
+            pixel[channel] = normal[channel] / length * 0.5 + 0.5;
+        }
+
+        if let Some(power) = options.toksvig_power {
+            let length = length.min(1.0);
+            pixel[3] *= length / (length + power * (1.0 - length));
+        }
+    }
+}
+
+/// Resize an image, sampling across the edges for wrapping directions
+///
+/// The image is extended with wrapped copies of itself before resizing and the extension is
+/// cropped off afterwards. The extension is picked so it scales to a whole number of pixels,
+/// keeping the sample positions identical to a plain resize.
+fn downscale(
+    image: &Rgba32FImage,
+    width: u32,
+    height: u32,
+    options: &MipmapOptions,
+) -> Rgba32FImage {
+    let (source_pad_x, pad_x) =
+        wrap_padding(image.width(), width, options.address_u.unwrap_or_default());
+    let (source_pad_y, pad_y) = wrap_padding(
+        image.height(),
+        height,
+        options.address_v.unwrap_or_default(),
+    );
+
+    if source_pad_x == 0 && source_pad_y == 0 {
+        return imageops::resize(image, width, height, options.filter);
+    }
+
+    let padded = ImageBuffer::from_fn(
+        image.width() + source_pad_x * 2,
+        image.height() + source_pad_y * 2,
+        |x, y| {
+            let source_x = (x as i64 - source_pad_x as i64).rem_euclid(image.width() as i64);
+            let source_y = (y as i64 - source_pad_y as i64).rem_euclid(image.height() as i64);
+            *image.get_pixel(source_x as u32, source_y as u32)
+        },
+    );
+    let resized = imageops::resize(
+        &padded,
+        width + pad_x * 2,
+        height + pad_y * 2,
+        options.filter,
+    );
+    imageops::crop_imm(&resized, pad_x, pad_y, width, height).to_image()
+}
+
+/// Number of source and target pixels to extend an edge by when wrapping
+fn wrap_padding(source: u32, target: u32, mode: AddressMode) -> (u32, u32) {
+    // wide enough for the support of all filters when downscaling by 2
+    const MIN_PADDING: u32 = 8;
+
+    if mode == AddressMode::Clamp || source == target {
+        return (0, 0);
+    }
+
+    let divisor = gcd(source, target);
+    let (source_step, target_step) = (source / divisor, target / divisor);
+    let steps = (MIN_PADDING + source_step - 1) / source_step;
+    (source_step * steps, target_step * steps)
+}
+
+fn gcd(a: u32, b: u32) -> u32 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+fn convert_color(image: &mut Rgba32FImage, convert: fn(f32) -> f32) {
+    for pixel in image.pixels_mut() {
+        for channel in 0..3 {
+            pixel[channel] = convert(pixel[channel]);
+        }
+    }
+}
+
+/// Fraction of the pixels that pass an alpha test with the given cutoff
+fn alpha_coverage(image: &Rgba32FImage, cutoff: f32) -> f32 {
+    let covered = image.pixels().filter(|pixel| pixel[3] > cutoff).count();
+    covered as f32 / (image.width() * image.height()) as f32
+}
+
+/// Scale the alpha of an image so that `target` of the pixels pass an alpha test with `cutoff`
+fn scale_alpha_to_coverage(image: &mut Rgba32FImage, cutoff: f32, target: f32) {
+    // find the cutoff that would give the desired coverage for the unscaled alpha
+    let mut low = 0.0;
+    let mut high = 1.0;
+    let mut threshold = cutoff;
+    for _ in 0..16 {
+        threshold = (low + high) / 2.0;
+        if alpha_coverage(image, threshold) > target {
+            low = threshold;
+        } else {
+            high = threshold;
+        }
+    }
+
+    let scale = cutoff / threshold.max(f32::EPSILON);
+    for pixel in image.pixels_mut() {
+        pixel[3] = (pixel[3] * scale).min(1.0);
+    }
+}
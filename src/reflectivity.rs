@@ -0,0 +1,55 @@
+use crate::color::{is_linear, srgb_to_linear};
+use image::DynamicImage;
+
+/// Settings for computing the reflectivity stored in the header
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ReflectivityOptions {
+    /// Weigh the color of every pixel by its alpha, so transparent pixels don't contribute
+    pub alpha_weighted: bool,
+    /// Convert sRGB encoded input to linear before averaging
+    ///
+    /// Enabled when not set, like vtex does. When building through
+    /// [`VTFBuilder`](crate::VTFBuilder) an unset value follows
+    /// [`VTFBuilder::gamma_correct`](crate::VTFBuilder::gamma_correct).
+    pub gamma_correct: Option<bool>,
+}
+
+/// Compute the average linear color of a set of frames, as used by vrad for radiosity
+///
+/// Float images are always treated as linear, integer images are linearized unless
+/// `gamma_correct` is disabled.
+pub fn compute_reflectivity(frames: &[DynamicImage], options: &ReflectivityOptions) -> [f32; 3] {
+    let mut sum = [0.0f64; 3];
+    let mut total_weight = 0.0f64;
+
+    for frame in frames {
+        let linear = is_linear(frame) || options.gamma_correct == Some(false);
+
+        for pixel in frame.to_rgba32f().pixels() {
+            let weight = if options.alpha_weighted {
+                pixel[3].clamp(0.0, 1.0) as f64
+            } else {
+                1.0
+            };
+            for channel in 0..3 {
+                let value = if linear {
+                    pixel[channel]
+                } else {
+                    srgb_to_linear(pixel[channel])
+                };
+                sum[channel] += value as f64 * weight;
+            }
+            total_weight += weight;
+        }
+    }
+
+    if total_weight == 0.0 {
+        return [0.0, 0.0, 0.0];
+    }
+
+    [
+        (sum[0] / total_weight) as f32,
+        (sum[1] / total_weight) as f32,
+        (sum[2] / total_weight) as f32,
+    ]
+}
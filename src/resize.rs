@@ -0,0 +1,103 @@
+use crate::Error;
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, ImageBuffer, Rgba};
+
+/// How images with a width or height that isn't a power of two are handled on creation
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ResizePolicy {
+    /// Reject the image with [`Error::InvalidImageSize`]
+    #[default]
+    Strict,
+    /// Scale to whichever power of two is closest
+    Nearest(FilterType),
+    /// Scale up to the next power of two
+    Next(FilterType),
+    /// Scale down to the previous power of two
+    Previous(FilterType),
+    /// Extend to the next power of two, filling the new area with the given color
+    Pad(Rgba<u8>),
+    /// Write the image at its original size, only supported by newer engine branches
+    AllowNonPowerOfTwo,
+}
+
+impl ResizePolicy {
+    /// Apply the policy to an image, returning an image that can be written to a vtf
+    pub fn apply(&self, image: &DynamicImage) -> Result<DynamicImage, Error> {
+        let width = image.width();
+        let height = image.height();
+
+        let resized = if width.is_power_of_two() && height.is_power_of_two() {
+            image.clone()
+        } else {
+            match *self {
+                ResizePolicy::Strict => return Err(Error::InvalidImageSize),
+                ResizePolicy::Nearest(filter) => {
+                    image.resize_exact(nearest(width), nearest(height), filter)
+                }
+                ResizePolicy::Next(filter) => image.resize_exact(next(width), next(height), filter),
+                ResizePolicy::Previous(filter) => {
+                    image.resize_exact(previous(width), previous(height), filter)
+                }
+                ResizePolicy::Pad(color) => pad(image, next(width), next(height), color),
+                ResizePolicy::AllowNonPowerOfTwo => image.clone(),
+            }
+        };
+
+        if resized.width() == 0
+            || resized.height() == 0
+            || resized.width() > u16::MAX as u32
+            || resized.height() > u16::MAX as u32
+        {
+            return Err(Error::InvalidImageSize);
+        }
+
+        Ok(resized)
+    }
+}
+
+fn next(size: u32) -> u32 {
+    size.next_power_of_two()
+}
+
+fn previous(size: u32) -> u32 {
+    if size.is_power_of_two() {
+        size
+    } else {
+        size.next_power_of_two() / 2
+    }
+}
+
+fn nearest(size: u32) -> u32 {
+    let (previous, next) = (previous(size), next(size));
+    if size - previous < next - size {
+        previous
+    } else {
+        next
+    }
+}
+
+/// Place the image in the top left corner of a larger canvas, keeping the precision of the input
+fn pad(image: &DynamicImage, width: u32, height: u32, color: Rgba<u8>) -> DynamicImage {
+    match image {
+        DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+            let color = Rgba(color.0.map(|channel| channel as f32 / 255.0));
+            let mut canvas = ImageBuffer::from_pixel(width, height, color);
+            imageops::replace(&mut canvas, &image.to_rgba32f(), 0, 0);
+            DynamicImage::ImageRgba32F(canvas)
+        }
+        DynamicImage::ImageLuma16(_)
+        | DynamicImage::ImageLumaA16(_)
+        | DynamicImage::ImageRgb16(_)
+        | DynamicImage::ImageRgba16(_) => {
+            let color = Rgba(color.0.map(|channel| channel as u16 * 257));
+            let mut canvas = ImageBuffer::from_pixel(width, height, color);
+            imageops::replace(&mut canvas, &image.to_rgba16(), 0, 0);
+            DynamicImage::ImageRgba16(canvas)
+        }
+        _ => {
+            let mut canvas = ImageBuffer::from_pixel(width, height, color);
+            imageops::replace(&mut canvas, &image.to_rgba8(), 0, 0);
+            DynamicImage::ImageRgba8(canvas)
+        }
+    }
+}
@@ -1,11 +1,61 @@
 use crate::Error;
-use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use byteorder::{ByteOrder, LittleEndian, ReadBytesExt, WriteBytesExt};
 use std::io::{Read, Write};
 
+/// Known resource ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResourceId {
+    /// Low resolution thumbnail image data, stored without a length prefix
+    LowResImage,
+    /// High resolution image data, stored without a length prefix
+    Image,
+    /// Particle sheet sequences
+    Sheet,
+    /// CRC of the source image
+    Crc,
+    /// Texture LOD clamping
+    LodControl,
+    /// Extended texture flags
+    ExtendedFlags,
+    /// Arbitrary KeyValues data
+    KeyValues,
+    Unknown([u8; 3]),
+}
+
+impl ResourceId {
+    pub fn from_id(id: [u8; 3]) -> Self {
+        match &id {
+            [0x01, 0x00, 0x00] => ResourceId::LowResImage,
+            [0x30, 0x00, 0x00] => ResourceId::Image,
+            [0x10, 0x00, 0x00] => ResourceId::Sheet,
+            b"CRC" => ResourceId::Crc,
+            b"LOD" => ResourceId::LodControl,
+            // VTF_RSRC_TEXTURE_SETTINGS_EX, MK_VTF_RSRC_ID('T','S','O') in the Source SDK vtf.h
+            b"TSO" => ResourceId::ExtendedFlags,
+            b"KVD" => ResourceId::KeyValues,
+            _ => ResourceId::Unknown(id),
+        }
+    }
+
+    pub fn id(&self) -> [u8; 3] {
+        match self {
+            ResourceId::LowResImage => [0x01, 0x00, 0x00],
+            ResourceId::Image => [0x30, 0x00, 0x00],
+            ResourceId::Sheet => [0x10, 0x00, 0x00],
+            ResourceId::Crc => *b"CRC",
+            ResourceId::LodControl => *b"LOD",
+            ResourceId::ExtendedFlags => *b"TSO",
+            ResourceId::KeyValues => *b"KVD",
+            ResourceId::Unknown(id) => *id,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct ResourceType {
-    id: [u8; 3],
-    flags: u8,
+    pub id: [u8; 3],
+    pub flags: u8,
 }
 
 impl ResourceType {
@@ -18,11 +68,57 @@ impl ResourceType {
         flags: 0,
     };
 
-    const HAS_NO_DATA_CHUNK: u8 = 0x02;
+    /// CRC-32 of the source image, stored inline
+    pub const CRC: ResourceType = ResourceType {
+        id: *b"CRC",
+        flags: Self::HAS_NO_DATA_CHUNK,
+    };
+
+    /// Resolution clamp for texture LOD, stored inline
+    pub const LOD_CONTROL: ResourceType = ResourceType {
+        id: *b"LOD",
+        flags: Self::HAS_NO_DATA_CHUNK,
+    };
+
+    /// Extended texture flags, stored inline
+    pub const EXTENDED_FLAGS: ResourceType = ResourceType {
+        id: *b"TSO",
+        flags: Self::HAS_NO_DATA_CHUNK,
+    };
+
+    /// Arbitrary KeyValues text
+    pub const KEY_VALUES: ResourceType = ResourceType {
+        id: *b"KVD",
+        flags: 0,
+    };
+
+    /// Particle sprite sheet
+    pub const SHEET: ResourceType = ResourceType {
+        id: [0x10, 0x00, 0x00],
+        flags: 0,
+    };
+
+    pub const HAS_NO_DATA_CHUNK: u8 = 0x02;
 
     pub fn has_resource_type(&self) -> bool {
+        self.has_data_chunk()
+    }
+
+    /// Whether the resource data is an offset into the file instead of an inline value
+    pub fn has_data_chunk(&self) -> bool {
         self.flags & Self::HAS_NO_DATA_CHUNK == 0
     }
+
+    pub fn resource_id(&self) -> ResourceId {
+        ResourceId::from_id(self.id)
+    }
+
+    pub(crate) fn is_image(&self) -> bool {
+        matches!(
+            self.resource_id(),
+            ResourceId::LowResImage | ResourceId::Image
+        )
+    }
 }
 
 impl ResourceType {
@@ -42,13 +138,73 @@ impl ResourceType {
     }
 }
 
+/// Value of the LOD control resource
+///
+/// The clamps limit the largest mip level that gets loaded, as log2 of the resolution in each
+/// direction. The Xbox 360 clamps are kept so existing values are written back unchanged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct LodControl {
+    pub resolution_clamp_u: u8,
+    pub resolution_clamp_v: u8,
+    pub resolution_clamp_u_360: u8,
+    pub resolution_clamp_v_360: u8,
+}
+
+impl From<u32> for LodControl {
+    fn from(value: u32) -> Self {
+        let [u, v, u_360, v_360] = value.to_le_bytes();
+        LodControl {
+            resolution_clamp_u: u,
+            resolution_clamp_v: v,
+            resolution_clamp_u_360: u_360,
+            resolution_clamp_v_360: v_360,
+        }
+    }
+}
+
+impl From<LodControl> for u32 {
+    fn from(lod: LodControl) -> Self {
+        u32::from_le_bytes([
+            lod.resolution_clamp_u,
+            lod.resolution_clamp_v,
+            lod.resolution_clamp_u_360,
+            lod.resolution_clamp_v_360,
+        ])
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone, Copy)]
 pub struct Resource {
-    ty: ResourceType,
+    pub ty: ResourceType,
     pub data: u32, // crc or offset
 }
 
 impl Resource {
+    pub fn new(ty: ResourceType, data: u32) -> Self {
+        Resource { ty, data }
+    }
+
+    /// Payload of the data chunk this resource points to, read through the length prefix at
+    /// its offset in `file`
+    ///
+    /// Resources with an inline value and the image resources, which have no length prefix,
+    /// return `None`.
+    pub fn data_chunk<'a>(&self, file: &'a [u8]) -> Result<Option<&'a [u8]>, Error> {
+        if !self.ty.has_data_chunk() || self.ty.is_image() {
+            return Ok(None);
+        }
+
+        let start = self.data as usize + 4;
+        let length = file
+            .get(self.data as usize..start)
+            .map(LittleEndian::read_u32)
+            .ok_or(Error::ResourceOutOfBounds(self.ty, self.data))?;
+        file.get(start..start + length as usize)
+            .map(Some)
+            .ok_or(Error::ResourceOutOfBounds(self.ty, self.data))
+    }
+
     pub fn read(bytes: &mut impl Read) -> Result<Self, Error> {
         Ok(Resource {
             ty: ResourceType::read(bytes)?,
@@ -63,6 +219,7 @@ impl Resource {
     }
 }
 
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[derive(Debug, Clone)]
 pub struct ResourceList {
     pub resources: Vec<Resource>,
@@ -93,4 +250,21 @@ impl ResourceList {
     pub fn get_by_type(&self, ty: ResourceType) -> Option<&Resource> {
         self.resources.iter().find(|resource| resource.ty == ty)
     }
+
+    pub fn get_by_type_mut(&mut self, ty: ResourceType) -> Option<&mut Resource> {
+        self.resources.iter_mut().find(|resource| resource.ty == ty)
+    }
+
+    /// First resource with the given id, regardless of its flags or the spelling of the id
+    pub fn get_by_id(&self, id: ResourceId) -> Option<&Resource> {
+        self.resources
+            .iter()
+            .find(|resource| resource.ty.resource_id() == id)
+    }
+
+    pub fn get_by_id_mut(&mut self, id: ResourceId) -> Option<&mut Resource> {
+        self.resources
+            .iter_mut()
+            .find(|resource| resource.ty.resource_id() == id)
+    }
 }
@@ -0,0 +1,164 @@
+use crate::Error;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use image::DynamicImage;
+use std::io::{Read, Write};
+use std::vec::Vec;
+
+/// Texture coordinates of a sprite in the sheet, from the top left to the bottom right corner
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct UvRect {
+    pub u0: f32,
+    pub v0: f32,
+    pub u1: f32,
+    pub v1: f32,
+}
+
+impl UvRect {
+    /// Cut the sprite out of the decoded sheet image
+    ///
+    /// Partially covered pixels are included, so rectangles that are inset by half a texel
+    /// still give the full sprite.
+    pub fn crop(&self, image: &DynamicImage) -> DynamicImage {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let x0 = (self.u0 * width).floor().clamp(0.0, width - 1.0) as u32;
+        let y0 = (self.v0 * height).floor().clamp(0.0, height - 1.0) as u32;
+        let x1 = (self.u1 * width).ceil().clamp(0.0, width) as u32;
+        let y1 = (self.v1 * height).ceil().clamp(0.0, height) as u32;
+        image.crop_imm(
+            x0,
+            y0,
+            x1.saturating_sub(x0).max(1),
+            y1.saturating_sub(y0).max(1),
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetFrame {
+    /// How long the frame is shown, in seconds
+    pub duration: f32,
+    /// One rectangle for version 0 sheets, four for version 1 sheets which can blend
+    /// multiple images per frame
+    pub coords: Vec<UvRect>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetSequence {
+    pub id: u32,
+    /// Stop at the last frame instead of looping
+    pub clamp: bool,
+    /// Length of the whole sequence in seconds
+    pub total_time: f32,
+    pub frames: Vec<SheetFrame>,
+}
+
+impl SheetSequence {
+    /// Cut the first image of every frame out of the decoded sheet, with its duration
+    pub fn extract(&self, image: &DynamicImage) -> Vec<(DynamicImage, f32)> {
+        self.frames
+            .iter()
+            .filter_map(|frame| {
+                let rect = frame.coords.first()?;
+                Some((rect.crop(image), frame.duration))
+            })
+            .collect()
+    }
+}
+
+/// Sprite sheet of a particle texture, stored in the sheet resource
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sheet {
+    pub version: u32,
+    pub sequences: Vec<SheetSequence>,
+}
+
+impl Sheet {
+    /// Number of rectangles stored for every frame in a version 1 sheet
+    const COORDS_PER_FRAME: usize = 4;
+
+    fn coords_per_frame(version: u32) -> Result<usize, Error> {
+        match version {
+            0 => Ok(1),
+            1 => Ok(Self::COORDS_PER_FRAME),
+            _ => Err(Error::InvalidSheet(format!(
+                "unsupported version {version}"
+            ))),
+        }
+    }
+
+    pub fn read(bytes: &mut impl Read) -> Result<Self, Error> {
+        let version = bytes.read_u32::<LittleEndian>()?;
+        let coords_per_frame = Self::coords_per_frame(version)?;
+        let sequence_count = bytes.read_u32::<LittleEndian>()?;
+
+        let sequences = (0..sequence_count)
+            .map(|_| {
+                let id = bytes.read_u32::<LittleEndian>()?;
+                let clamp = bytes.read_u32::<LittleEndian>()? != 0;
+                let frame_count = bytes.read_u32::<LittleEndian>()?;
+                let total_time = bytes.read_f32::<LittleEndian>()?;
+                let frames = (0..frame_count)
+                    .map(|_| {
+                        let duration = bytes.read_f32::<LittleEndian>()?;
+                        let coords = (0..coords_per_frame)
+                            .map(|_| {
+                                Ok(UvRect {
+                                    u0: bytes.read_f32::<LittleEndian>()?,
+                                    v0: bytes.read_f32::<LittleEndian>()?,
+                                    u1: bytes.read_f32::<LittleEndian>()?,
+                                    v1: bytes.read_f32::<LittleEndian>()?,
+                                })
+                            })
+                            .collect::<Result<Vec<_>, Error>>()?;
+                        Ok(SheetFrame { duration, coords })
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(SheetSequence {
+                    id,
+                    clamp,
+                    total_time,
+                    frames,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        Ok(Sheet { version, sequences })
+    }
+
+    pub fn write(&self, bytes: &mut impl Write) -> Result<(), Error> {
+        let coords_per_frame = Self::coords_per_frame(self.version)?;
+        bytes.write_u32::<LittleEndian>(self.version)?;
+        bytes.write_u32::<LittleEndian>(self.sequences.len() as u32)?;
+
+        for sequence in &self.sequences {
+            bytes.write_u32::<LittleEndian>(sequence.id)?;
+            bytes.write_u32::<LittleEndian>(sequence.clamp as u32)?;
+            bytes.write_u32::<LittleEndian>(sequence.frames.len() as u32)?;
+            bytes.write_f32::<LittleEndian>(sequence.total_time)?;
+
+            for frame in &sequence.frames {
+                if frame.coords.len() != coords_per_frame {
+                    return Err(Error::InvalidSheet(format!(
+                        "version {} sheets need {} coordinates per frame, found {}",
+                        self.version,
+                        coords_per_frame,
+                        frame.coords.len()
+                    )));
+                }
+                bytes.write_f32::<LittleEndian>(frame.duration)?;
+                for rect in &frame.coords {
+                    bytes.write_f32::<LittleEndian>(rect.u0)?;
+                    bytes.write_f32::<LittleEndian>(rect.v0)?;
+                    bytes.write_f32::<LittleEndian>(rect.u1)?;
+                    bytes.write_f32::<LittleEndian>(rect.v1)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn sequence(&self, id: u32) -> Option<&SheetSequence> {
+        self.sequences.iter().find(|sequence| sequence.id == id)
+    }
+}
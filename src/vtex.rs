@@ -0,0 +1,149 @@
+use crate::flags::TextureFlags;
+use crate::keyvalues::KeyValues;
+use crate::Error;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Extensions looked for when locating the source image for a `.txt` file
+///
+/// vtex also accepts `psd` and `pfm` files, which can't be decoded.
+const SOURCE_EXTENSIONS: [&str; 4] = ["tga", "png", "bmp", "tif"];
+
+/// Compile settings from a vtex `.txt` file next to the source image
+///
+/// Unknown keys are ignored.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CompileParams {
+    pub nomip: bool,
+    pub nolod: bool,
+    pub clamps: bool,
+    pub clampt: bool,
+    pub clampu: bool,
+    pub pointsample: bool,
+    pub trilinear: bool,
+    pub anisotropic: bool,
+    /// Store the texture uncompressed instead of using DXT
+    pub nocompress: bool,
+    /// The source image is a normal map
+    pub normal: bool,
+    /// The source image is a self shadowing bump map
+    pub ssbump: bool,
+    /// The texture is a skybox face, which is clamped to prevent seams between faces
+    pub skybox: bool,
+    /// Frame number of the first source image of an animated texture
+    pub start_frame: Option<u32>,
+    /// Frame number of the last source image of an animated texture
+    pub end_frame: Option<u32>,
+    pub bumpscale: Option<f32>,
+}
+
+impl CompileParams {
+    pub fn parse(input: &str) -> Result<Self, Error> {
+        Self::from_keyvalues(&KeyValues::parse(input)?)
+    }
+
+    pub fn from_keyvalues(keyvalues: &KeyValues) -> Result<Self, Error> {
+        let flag = |key: &str| -> Result<bool, Error> {
+            Ok(parse_value::<i32>(keyvalues, key)?.map_or(false, |value| value != 0))
+        };
+
+        Ok(CompileParams {
+            nomip: flag("nomip")?,
+            nolod: flag("nolod")?,
+            clamps: flag("clamps")?,
+            clampt: flag("clampt")?,
+            clampu: flag("clampu")?,
+            pointsample: flag("pointsample")?,
+            trilinear: flag("trilinear")?,
+            anisotropic: flag("anisotropic")?,
+            nocompress: flag("nocompress")?,
+            normal: flag("normal")?,
+            ssbump: flag("ssbump")?,
+            skybox: flag("skybox")?,
+            start_frame: parse_value(keyvalues, "startframe")?,
+            end_frame: parse_value(keyvalues, "endframe")?,
+            bumpscale: parse_value(keyvalues, "bumpscale")?,
+        })
+    }
+
+    /// Header flags set by these parameters
+    ///
+    /// Flags that depend on the image content, like the alpha flags, are not included.
+    pub fn flags(&self) -> TextureFlags {
+        let mut flags = TextureFlags::empty();
+        flags.set(TextureFlags::POINTSAMPLE, self.pointsample);
+        flags.set(TextureFlags::TRILINEAR, self.trilinear);
+        flags.set(TextureFlags::CLAMPS, self.clamps || self.skybox);
+        flags.set(TextureFlags::CLAMPT, self.clampt || self.skybox);
+        flags.set(TextureFlags::ANISOTROPIC, self.anisotropic);
+        flags.set(TextureFlags::NORMAL, self.normal);
+        flags.set(TextureFlags::NOMIP, self.nomip);
+        flags.set(TextureFlags::NOLOD, self.nolod);
+        flags.set(TextureFlags::CLAMPU, self.clampu);
+        flags.set(TextureFlags::SSBUMP, self.ssbump);
+        flags
+    }
+
+    /// Read the parameter file belonging to a source image, if it exists
+    pub fn for_image(path: &Path) -> Result<Option<Self>, Error> {
+        let params_path = path.with_extension("txt");
+        if !params_path.is_file() {
+            return Ok(None);
+        }
+        Self::parse(&std::fs::read_to_string(params_path)?).map(Some)
+    }
+
+    /// Paths of the source images for a texture
+    ///
+    /// `path` can either point to the source image or to the parameter file, for animated
+    /// textures the frame number is appended to the file name as three digits.
+    pub fn source_paths(&self, path: &Path) -> Result<Vec<PathBuf>, Error> {
+        let stem = path.with_extension("");
+        let extension = path
+            .extension()
+            .filter(|extension| !extension.eq_ignore_ascii_case("txt"));
+
+        let find = |base: PathBuf| -> Result<PathBuf, Error> {
+            if let Some(extension) = extension {
+                return Ok(base.with_extension(extension));
+            }
+            SOURCE_EXTENSIONS
+                .iter()
+                .map(|extension| base.with_extension(extension))
+                .find(|path| path.is_file())
+                .ok_or_else(|| {
+                    Error::Io(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("no source image found for {}", base.display()),
+                    ))
+                })
+        };
+
+        match (self.start_frame, self.end_frame) {
+            (Some(start), Some(end)) if start > end => Err(Error::InvalidCompileParameter(
+                "endframe".into(),
+                end.to_string(),
+            )),
+            (Some(start), Some(end)) => (start..=end)
+                .map(|frame| {
+                    let mut name = stem.clone().into_os_string();
+                    name.push(format!("{:03}", frame));
+                    find(PathBuf::from(name))
+                })
+                .collect(),
+            _ => Ok(vec![find(stem)?]),
+        }
+    }
+}
+
+fn parse_value<T: FromStr>(keyvalues: &KeyValues, key: &str) -> Result<Option<T>, Error> {
+    keyvalues
+        .get_str(key)
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| Error::InvalidCompileParameter(key.into(), value.into()))
+        })
+        .transpose()
+}
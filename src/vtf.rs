@@ -1,46 +1,36 @@
-use crate::header::VTFHeader;
+use crate::builder::VTFBuilder;
+use crate::encode::{AutoFormatOptions, EncodeOptions};
+use crate::header::{ReadMode, VTFHeader};
 use crate::image::{ImageFormat, VTFImage};
-use crate::resources::{ResourceList, ResourceType};
+use crate::keyvalues::KeyValues;
+use crate::resources::{Resource, ResourceId, ResourceList, ResourceType};
+use crate::sheet::Sheet;
+use crate::utils::get_mip_size;
 use crate::Error;
 use image::DynamicImage;
 use std::io::Cursor;
 use std::vec::Vec;
-use texpresso::{Format, Params};
 
 #[derive(Debug)]
 pub struct VTF<'a> {
     pub header: VTFHeader,
     pub lowres_image: VTFImage<'a>,
     pub highres_image: VTFImage<'a>,
+    bytes: &'a [u8],
 }
 
 impl<'a> VTF<'a> {
     pub fn read(bytes: &'a [u8]) -> Result<VTF<'a>, Error> {
+        Self::read_with_mode(bytes, ReadMode::default())
+    }
+
+    /// Read a vtf file, validating the header with the given strictness
+    pub fn read_with_mode(bytes: &'a [u8], mode: ReadMode) -> Result<VTF<'a>, Error> {
         let mut cursor = Cursor::new(bytes);
 
         let header = VTFHeader::read(&mut cursor)?;
-
-        let lowres_offset = match header
-            .resources
-            .get_by_type(ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE)
-        {
-            Some(resource) => resource.data,
-            None => header.header_size,
-        };
-
-        let highres_offset = match header
-            .resources
-            .get_by_type(ResourceType::VTF_LEGACY_RSRC_IMAGE)
-        {
-            Some(resource) => resource.data,
-            None => {
-                lowres_offset
-                    + header.lowres_image_format.frame_size(
-                        header.lowres_image_width as u32,
-                        header.lowres_image_height as u32,
-                    )?
-            }
-        };
+        header.validate(bytes.len(), mode)?;
+        let (lowres_offset, highres_offset) = header.image_offsets()?;
 
         let lowres_image = VTFImage::new(
             header.clone(),
@@ -64,94 +54,217 @@ impl<'a> VTF<'a> {
             header,
             lowres_image,
             highres_image,
+            bytes,
         })
     }
 
     pub fn create(image: DynamicImage, image_format: ImageFormat) -> Result<Vec<u8>, Error> {
-        if !image.width().is_power_of_two()
-            || !image.height().is_power_of_two()
-            || image.width() > u16::MAX as u32
-            || image.height() > u16::MAX as u32
-        {
-            return Err(Error::InvalidImageSize);
+        Self::create_with_options(image, image_format, &EncodeOptions::default())
+    }
+
+    pub fn create_with_options(
+        image: DynamicImage,
+        image_format: ImageFormat,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, Error> {
+        VTFBuilder::new(image, image_format)
+            .encode_options(*options)
+            .build()
+    }
+
+    /// Create a vtf with the format picked from the image content
+    pub fn create_auto(image: DynamicImage, options: &AutoFormatOptions) -> Result<Vec<u8>, Error> {
+        VTFBuilder::auto(image, *options).build()
+    }
+
+    /// Payload of the first resource with the given id, see [`Resource::data_chunk`]
+    pub fn resource_data(&self, id: ResourceId) -> Result<Option<&'a [u8]>, Error> {
+        match self.header.resources.get_by_id(id) {
+            Some(resource) => resource.data_chunk(self.bytes),
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the KeyValues data from the KVD resource
+    pub fn key_values(&self) -> Result<Option<KeyValues>, Error> {
+        match self.resource_data(ResourceId::KeyValues)? {
+            Some(data) => {
+                let text = std::str::from_utf8(data)
+                    .map_err(|_| Error::InvalidKeyValues("data is not valid utf-8".into()))?;
+                KeyValues::parse(text.trim_end_matches('\0')).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Parse the particle sheet from the sheet resource
+    pub fn sheet(&self) -> Result<Option<Sheet>, Error> {
+        match self.resource_data(ResourceId::Sheet)? {
+            Some(mut data) => Sheet::read(&mut data).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Rewrite a vtf file as another 7.x version without re-encoding the image data
+    ///
+    /// The depth field and resource directory are added or removed as needed, converting to 7.5
+    /// drops the spheremap face of environment maps. Resources other than the images are lost
+    /// when converting to a version before 7.3.
+    ///
+    /// Versions before 7.5 use the first frame field to mark environment maps without a spheremap,
+    /// so converting a 7.5 environment map that starts at a frame other than 0 to an older
+    /// version fails.
+    pub fn convert_version(bytes: &[u8], version: [u32; 2]) -> Result<Vec<u8>, Error> {
+        if version[0] != 7 || version[1] > 5 {
+            return Err(Error::UnsupportedVersion(version[0], version[1]));
         }
 
-        let header = VTFHeader {
-            signature: VTFHeader::SIGNATURE,
-            version: [7, 1], // simpler version without resources for now
-            header_size: 64,
-            width: image.width() as u16,
-            height: image.height() as u16,
-            flags: 8972,
-            frames: 1,
-            first_frame: 0,
-            reflectivity: [0.0, 0.0, 0.0],
-            bumpmap_scale: 1.0,
-            highres_image_format: image_format,
-            mipmap_count: 1,
-            lowres_image_format: ImageFormat::Dxt1, // always the case
-            lowres_image_width: 0,                  // no lowres for now
-            lowres_image_height: 0,
-            depth: 1,
-            resources: ResourceList::empty(),
+        let source = VTFHeader::read(&mut Cursor::new(bytes))?;
+        Self::rewrite(bytes, source, version, |_| {})
+    }
+
+    /// Add or replace a resource that stores its value inline, like the CRC or LOD control
+    ///
+    /// The file is rewritten without re-encoding the image data and needs to be version 7.3 or
+    /// newer to store resources.
+    pub fn set_resource_value(
+        bytes: &[u8],
+        ty: ResourceType,
+        value: u32,
+    ) -> Result<Vec<u8>, Error> {
+        let source = VTFHeader::read(&mut Cursor::new(bytes))?;
+        if source.version[1] < 3 {
+            return Err(Error::ResourcesNotSupported(
+                source.version[0],
+                source.version[1],
+            ));
+        }
+
+        let ty = ResourceType {
+            flags: ty.flags | ResourceType::HAS_NO_DATA_CHUNK,
+            ..ty
         };
+        let version = source.version;
+        Self::rewrite(bytes, source, version, |resources| {
+            match resources.get_by_id_mut(ty.resource_id()) {
+                Some(resource) if !resource.ty.has_data_chunk() => resource.data = value,
+                _ => resources.resources.push(Resource::new(ty, value)),
+            }
+        })
+    }
 
-        let mut data = Vec::with_capacity(
-            header.header_size as usize
-                + header
-                    .highres_image_format
-                    .frame_size(header.width as u32, header.height as u32)?
-                    as usize
-                + header.lowres_image_format.frame_size(
-                    header.lowres_image_width as u32,
-                    header.lowres_image_height as u32,
-                )? as usize,
-        );
+    /// Write the file as `version`, with `edit` applied to the resource directory
+    ///
+    /// `edit` can only change resources that store their value inline.
+    fn rewrite(
+        bytes: &[u8],
+        source: VTFHeader,
+        version: [u32; 2],
+        edit: impl FnOnce(&mut ResourceList),
+    ) -> Result<Vec<u8>, Error> {
+        source.validate(bytes.len(), ReadMode::Lenient)?;
+        if version[1] < 2 && source.depth > 1 {
+            return Err(Error::UnsupportedVolumeTexture(version[0], version[1]));
+        }
+        let (lowres_offset, highres_offset) = source.image_offsets()?;
 
-        header.write(&mut data)?;
+        let mut header = source.clone();
+        header.version = version;
+        // versions before 7.5 store a spheremap face unless the first frame is set to -1
+        if version[1] < 5 && source.faces() == 6 {
+            if source.version[1] >= 5 && source.first_frame != 0 {
+                return Err(Error::UnsupportedEnvmapFirstFrame(
+                    version[0],
+                    version[1],
+                    source.first_frame,
+                ));
+            }
+            header.first_frame = 0xffff;
+        }
+
+        let lowres_size = source.lowres_image_format.frame_size(
+            source.lowres_image_width as u32,
+            source.lowres_image_height as u32,
+        )?;
+        let lowres = slice(bytes, lowres_offset, lowres_size)?;
 
-        let header_size = header.size();
-        assert!(data.len() <= header_size, "invalid header size");
-
-        data.resize(header_size, 0);
-
-        let width = header.width as usize;
-        let height = header.height as usize;
-
-        match image_format {
-            ImageFormat::Dxt5 => {
-                let image_data = image.to_rgba8();
-                data.resize(header_size + Format::Bc3.compressed_size(width, height), 0);
-                Format::Bc3.compress(
-                    image_data.as_raw(),
-                    width,
-                    height,
-                    Params::default(),
-                    &mut data[header_size..],
-                );
+        let mut highres = Vec::new();
+        let mut offset = highres_offset;
+        for level in (0..source.mipmap_count as u32).rev() {
+            let size = get_mip_size(&source, &source.highres_image_format, level, source.depth)?;
+            for _ in 0..source.frames {
+                for face in 0..source.faces() {
+                    if face < header.faces() {
+                        highres.extend_from_slice(slice(bytes, offset, size)?);
+                    }
+                    offset += size;
+                }
             }
-            ImageFormat::Dxt1Onebitalpha => {
-                let image_data = image.to_rgba8();
-                data.resize(header_size + Format::Bc1.compressed_size(width, height), 0);
-                Format::Bc1.compress(
-                    image_data.as_raw(),
-                    width,
-                    height,
-                    Params::default(),
-                    &mut data[header_size..],
-                );
+        }
+
+        let mut chunks = Vec::new();
+        header.resources = ResourceList::empty();
+        if version[1] >= 3 && source.version[1] >= 3 {
+            for resource in &source.resources.resources {
+                if let Some(chunk) = resource.data_chunk(bytes)? {
+                    chunks.push(chunk);
+                }
+                header.resources.resources.push(*resource);
             }
-            ImageFormat::Rgba8888 => {
-                let image_data = image.to_rgba8();
-                data.extend_from_slice(&image_data);
+        } else if version[1] >= 3 {
+            if lowres_size > 0 {
+                header.resources.resources.push(Resource::new(
+                    ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE,
+                    0,
+                ));
             }
-            ImageFormat::Rgb888 => {
-                let image_data = image.to_rgb8();
-                data.extend_from_slice(&image_data);
+            header
+                .resources
+                .resources
+                .push(Resource::new(ResourceType::VTF_LEGACY_RSRC_IMAGE, 0));
+        }
+        if version[1] >= 3 {
+            edit(&mut header.resources);
+        }
+        header.header_size = header.size() as u32;
+
+        // resource data is stored between the header and the images
+        let mut offset = header.header_size;
+        let mut chunk_sizes = chunks.iter().map(|chunk| chunk.len() as u32 + 4);
+        for resource in &mut header.resources.resources {
+            if resource.ty.has_data_chunk() && !resource.ty.is_image() {
+                resource.data = offset;
+                offset += chunk_sizes.next().unwrap_or(0);
             }
-            _ => return Err(Error::UnsupportedEncodeImageFormat(image_format)),
+        }
+        if let Some(resource) = header
+            .resources
+            .get_by_type_mut(ResourceType::VTF_LEGACY_RSRC_LOW_RES_IMAGE)
+        {
+            resource.data = offset;
+        }
+        if let Some(resource) = header
+            .resources
+            .get_by_type_mut(ResourceType::VTF_LEGACY_RSRC_IMAGE)
+        {
+            resource.data = offset + lowres_size;
         }
 
+        let mut data = Vec::with_capacity(offset as usize + lowres.len() + highres.len());
+        header.write(&mut data)?;
+        data.resize(header.size(), 0);
+        for chunk in chunks {
+            data.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+            data.extend_from_slice(chunk);
+        }
+        data.extend_from_slice(lowres);
+        data.extend_from_slice(&highres);
         Ok(data)
     }
 }
+
+fn slice(bytes: &[u8], offset: u32, length: u32) -> Result<&[u8], Error> {
+    bytes
+        .get(offset as usize..offset as usize + length as usize)
+        .ok_or_else(|| Error::Io(std::io::ErrorKind::UnexpectedEof.into()))
+}
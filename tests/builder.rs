@@ -0,0 +1,351 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use vtf::encode::DxtQuality;
+use vtf::mipmap::{AddressMode, MipmapOptions, NormalMapOptions};
+use vtf::reflectivity::ReflectivityOptions;
+use vtf::resize::ResizePolicy;
+use vtf::{Error, ImageFormat, TextureFlags, VTFBuilder};
+
+fn solid_image(width: u32, height: u32, color: [u8; 4]) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, Rgba(color)))
+}
+
+#[test]
+fn test_builder_header() {
+    let data = VTFBuilder::new(solid_image(64, 32, [255, 0, 0, 255]), ImageFormat::Rgba8888)
+        .flags(TextureFlags::CLAMPS | TextureFlags::CLAMPT)
+        .first_frame(1)
+        .reflectivity([0.5, 0.25, 0.125])
+        .bumpmap_scale(2.0)
+        .version([7, 0])
+        .frame(solid_image(64, 32, [0, 255, 0, 255]))
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    assert_eq!([7, 0], vtf.header.version);
+    assert_eq!(
+        TextureFlags::CLAMPS | TextureFlags::CLAMPT,
+        vtf.header.flags
+    );
+    assert_eq!(2, vtf.header.frames);
+    assert_eq!(1, vtf.header.first_frame);
+    assert_eq!([0.5, 0.25, 0.125], vtf.header.reflectivity);
+    assert_eq!(2.0, vtf.header.bumpmap_scale);
+    assert_eq!(1, vtf.header.mipmap_count);
+
+    let frame = vtf.highres_image.decode(1).unwrap();
+    assert_eq!(Rgba([0, 255, 0, 255]), frame.get_pixel(10, 10));
+}
+
+#[test]
+fn test_builder_mipmaps_and_thumbnail() {
+    let data = VTFBuilder::new(solid_image(64, 32, [10, 20, 30, 255]), ImageFormat::Rgb888)
+        .mipmaps(true)
+        .thumbnail(true)
+        .frame(solid_image(64, 32, [40, 50, 60, 255]))
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    assert_eq!(7, vtf.header.mipmap_count);
+    assert_eq!(16, vtf.header.lowres_image_width);
+    assert_eq!(8, vtf.header.lowres_image_height);
+
+    let mip_sizes: u32 = (0..7)
+        .map(|level| (64 >> level).max(1) * (32 >> level).max(1))
+        .sum();
+    assert_eq!(64 + 64 + (mip_sizes as usize * 3 * 2), data.len());
+
+    let frame = vtf.highres_image.decode(1).unwrap();
+    assert_eq!(Rgba([40, 50, 60, 255]), frame.get_pixel(63, 31));
+}
+
+#[test]
+fn test_builder_write() {
+    let builder = VTFBuilder::new(solid_image(4, 4, [1, 2, 3, 4]), ImageFormat::Rgba8888);
+    let mut written = Vec::new();
+    builder.write(&mut written).unwrap();
+    assert_eq!(builder.build().unwrap(), written);
+}
+
+#[test]
+fn test_builder_frame_size_mismatch() {
+    let result = VTFBuilder::new(solid_image(4, 4, [0; 4]), ImageFormat::Rgba8888)
+        .frame(solid_image(8, 8, [0; 4]))
+        .build();
+    assert!(matches!(result, Err(Error::MismatchedFrameSize)));
+}
+
+#[test]
+fn test_builder_envmap_faces() {
+    for (version, faces) in [([7, 1], 7), ([7, 5], 6)] {
+        let data = VTFBuilder::new(solid_image(4, 4, [1, 2, 3, 4]), ImageFormat::Rgba8888)
+            .version(version)
+            .flags(TextureFlags::ENVMAP)
+            .build()
+            .unwrap();
+        let vtf = vtf::from_bytes(&data).unwrap();
+        assert_eq!(faces, vtf.header.faces());
+        assert_eq!(1, vtf.header.frames);
+        assert_eq!(
+            vtf.header.header_size as usize + faces as usize * 64,
+            data.len()
+        );
+    }
+
+    let mut builder = VTFBuilder::new(solid_image(4, 4, [0; 4]), ImageFormat::Rgba8888)
+        .version([7, 5])
+        .flags(TextureFlags::ENVMAP);
+    for _ in 0..11 {
+        builder = builder.frame(solid_image(4, 4, [0; 4]));
+    }
+    let vtf_data = builder.clone().build().unwrap();
+    assert_eq!(2, vtf::from_bytes(&vtf_data).unwrap().header.frames);
+    assert!(matches!(
+        builder.frame(solid_image(4, 4, [0; 4])).build(),
+        Err(Error::MismatchedFaceCount(13, 6))
+    ));
+}
+
+#[test]
+fn test_builder_address_mode() {
+    // white on the left edge, black everywhere else
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(16, 16, |x, _| {
+        if x == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    }));
+    // red channel of the right edge of the 8x8 mip level
+    let right_edge = |builder: VTFBuilder| {
+        let data = builder.mipmaps(true).build().unwrap();
+        let level = data.len() - 16 * 16 * 4 - 8 * 8 * 4;
+        data[level + 7 * 4]
+    };
+
+    let tiling = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888).flags(TextureFlags::empty());
+    let clamped = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+        .flags(TextureFlags::CLAMPS | TextureFlags::CLAMPT);
+    assert!(right_edge(tiling.clone()) > 0);
+    assert_eq!(0, right_edge(clamped.clone()));
+
+    // explicit mipmap options take precedence over the flags
+    let options = MipmapOptions {
+        address_u: Some(AddressMode::Wrap),
+        ..Default::default()
+    };
+    assert_eq!(
+        right_edge(tiling.clone()),
+        right_edge(clamped.clone().mipmap_options(options))
+    );
+    assert_eq!(
+        0,
+        right_edge(
+            clamped
+                .mipmap_options(options)
+                .address_mode(AddressMode::Clamp, AddressMode::Clamp)
+        )
+    );
+    let options = MipmapOptions {
+        address_u: Some(AddressMode::Clamp),
+        ..Default::default()
+    };
+    assert_eq!(0, right_edge(tiling.mipmap_options(options)));
+}
+
+#[test]
+fn test_builder_reflectivity() {
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 2, |x, _| {
+        if x == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    }));
+
+    let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert_eq!([0.5, 0.5, 0.5], vtf.header.reflectivity);
+
+    let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+        .reflectivity_options(ReflectivityOptions {
+            alpha_weighted: true,
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert_eq!([1.0, 1.0, 1.0], vtf.header.reflectivity);
+
+    let gray = solid_image(4, 4, [128, 128, 128, 255]);
+    let data = VTFBuilder::new(gray.clone(), ImageFormat::Rgba8888)
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert!((vtf.header.reflectivity[0] - 0.2158).abs() < 0.001);
+
+    let data = VTFBuilder::new(gray, ImageFormat::Rgba8888)
+        .flags(TextureFlags::SRGB)
+        .reflectivity_options(ReflectivityOptions {
+            gamma_correct: Some(false),
+            ..Default::default()
+        })
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert!((vtf.header.reflectivity[0] - 0.502).abs() < 0.001);
+}
+
+#[test]
+fn test_builder_gamma_correct() {
+    // black and mid gray average to different values in linear and sRGB space
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 2, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgba([128, 128, 128, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    }));
+    let srgb_to_linear = |value: f32| {
+        if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+
+    for gamma_correct in [false, true] {
+        let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+            .mipmaps(true)
+            .gamma_correct(gamma_correct)
+            .build()
+            .unwrap();
+        let vtf = vtf::from_bytes(&data).unwrap();
+
+        // the 1x1 mip level is stored first, and has to match the reflectivity
+        let mip = data[vtf.header.header_size as usize] as f32 / 255.0;
+        let mip = if gamma_correct {
+            srgb_to_linear(mip)
+        } else {
+            mip
+        };
+        let reflectivity = vtf.header.reflectivity[0];
+        assert!(
+            (mip - reflectivity).abs() < 0.01,
+            "mip {} and reflectivity {} differ",
+            mip,
+            reflectivity
+        );
+    }
+}
+
+#[test]
+fn test_builder_resize_policy() {
+    let image = solid_image(100, 40, [10, 20, 30, 255]);
+
+    let result = VTFBuilder::new(image.clone(), ImageFormat::Rgb888).build();
+    assert!(matches!(result, Err(Error::InvalidImageSize)));
+
+    let sizes = [
+        (ResizePolicy::Nearest(FilterType::Triangle), (128, 32)),
+        (ResizePolicy::Next(FilterType::Triangle), (128, 64)),
+        (ResizePolicy::Previous(FilterType::Triangle), (64, 32)),
+        (ResizePolicy::Pad(Rgba([0, 0, 0, 0])), (128, 64)),
+        (ResizePolicy::AllowNonPowerOfTwo, (100, 40)),
+    ];
+    for (policy, (width, height)) in sizes {
+        let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+            .resize_policy(policy)
+            .build()
+            .unwrap();
+        let vtf = vtf::from_bytes(&data).unwrap();
+        assert_eq!(width, vtf.header.width);
+        assert_eq!(height, vtf.header.height);
+    }
+}
+
+#[test]
+fn test_builder_pad() {
+    let data = VTFBuilder::new(solid_image(3, 2, [10, 20, 30, 255]), ImageFormat::Rgba8888)
+        .resize_policy(ResizePolicy::Pad(Rgba([1, 2, 3, 4])))
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    let image = vtf.highres_image.decode(0).unwrap();
+
+    assert_eq!((4, 2), image.dimensions());
+    assert_eq!(Rgba([10, 20, 30, 255]), image.get_pixel(2, 1));
+    assert_eq!(Rgba([1, 2, 3, 4]), image.get_pixel(3, 1));
+}
+
+#[test]
+fn test_builder_versions() {
+    let image = solid_image(32, 32, [10, 20, 30, 255]);
+    for minor in 0..=5 {
+        for thumbnail in [false, true] {
+            let data = VTFBuilder::new(image.clone(), ImageFormat::Rgb888)
+                .version([7, minor])
+                .mipmaps(true)
+                .thumbnail(thumbnail)
+                .build()
+                .unwrap();
+            let vtf = vtf::from_bytes(&data).unwrap();
+
+            let resources = match (minor, thumbnail) {
+                (0..=2, _) => 0,
+                (_, false) => 1,
+                (_, true) => 2,
+            };
+            let header_size = match minor {
+                0 | 1 => 64,
+                _ => 80 + resources * 8,
+            };
+            assert_eq!([7, minor], vtf.header.version);
+            assert_eq!(1, vtf.header.depth);
+            assert_eq!(resources, vtf.header.resources.resources.len());
+            assert_eq!(header_size as u32, vtf.header.header_size);
+
+            let frame = vtf.highres_image.decode(0).unwrap();
+            assert_eq!(Rgba([10, 20, 30, 255]), frame.get_pixel(31, 31));
+        }
+    }
+
+    let result = VTFBuilder::new(image, ImageFormat::Rgb888)
+        .version([7, 6])
+        .build();
+    assert!(matches!(result, Err(Error::UnsupportedVersion(7, 6))));
+}
+
+#[test]
+fn test_builder_dxt_quality() {
+    let image = solid_image(16, 16, [200, 100, 50, 255]);
+    for quality in [DxtQuality::FAST, DxtQuality::default(), DxtQuality::HIGH] {
+        let data = VTFBuilder::new(image.clone(), ImageFormat::Dxt5)
+            .dxt_quality(quality)
+            .build()
+            .unwrap();
+        assert_eq!(64 + 16 * 16, data.len());
+    }
+}
+
+#[test]
+fn test_builder_normal_map() {
+    let data = VTFBuilder::new(
+        solid_image(8, 8, [128, 128, 255, 255]),
+        ImageFormat::Rgba8888,
+    )
+    .flags(TextureFlags::CLAMPS)
+    .mipmaps(true)
+    .normal_map(NormalMapOptions::default())
+    .build()
+    .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert_eq!(
+        TextureFlags::CLAMPS | TextureFlags::NORMAL,
+        vtf.header.flags
+    );
+}
@@ -0,0 +1,86 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use vtf::vtf::VTF;
+use vtf::{Error, ImageFormat, TextureFlags, VTFBuilder};
+
+fn solid_image(color: [u8; 4]) -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba(color)))
+}
+
+#[test]
+fn test_convert_version() {
+    for source in 0..=5 {
+        let data = VTFBuilder::new(solid_image([10, 20, 30, 255]), ImageFormat::Rgba8888)
+            .frame(solid_image([40, 50, 60, 255]))
+            .version([7, source])
+            .mipmaps(true)
+            .thumbnail(true)
+            .build()
+            .unwrap();
+
+        for target in 0..=5 {
+            let converted = VTF::convert_version(&data, [7, target]).unwrap();
+            let vtf = vtf::from_bytes(&converted).unwrap();
+
+            let resources = if target >= 3 { 2 } else { 0 };
+            assert_eq!([7, target], vtf.header.version);
+            assert_eq!(resources, vtf.header.resources.resources.len());
+            assert_eq!(vtf.header.size() as u32, vtf.header.header_size);
+            assert_eq!(4, vtf.header.mipmap_count);
+            assert_eq!(
+                data.len() - vtf::from_bytes(&data).unwrap().header.size(),
+                converted.len() - vtf.header.size()
+            );
+
+            let frame = vtf.highres_image.decode(1).unwrap();
+            assert_eq!(Rgba([40, 50, 60, 255]), frame.get_pixel(7, 7));
+            let thumbnail = vtf.lowres_image.decode(0).unwrap();
+            assert_eq!((8, 8), thumbnail.dimensions());
+        }
+    }
+
+    let data = VTFBuilder::new(solid_image([0; 4]), ImageFormat::Rgba8888)
+        .build()
+        .unwrap();
+    assert!(matches!(
+        VTF::convert_version(&data, [7, 6]),
+        Err(Error::UnsupportedVersion(7, 6))
+    ));
+}
+
+#[test]
+fn test_convert_envmap() {
+    let mut builder = VTFBuilder::new(solid_image([0, 0, 0, 255]), ImageFormat::Rgba8888)
+        .version([7, 4])
+        .flags(TextureFlags::ENVMAP);
+    for face in 1..7 {
+        builder = builder.frame(solid_image([face * 30, 0, 0, 255]));
+    }
+    let data = builder.build().unwrap();
+
+    let face_size = 8 * 8 * 4;
+    let converted = VTF::convert_version(&data, [7, 5]).unwrap();
+    let vtf = vtf::from_bytes(&converted).unwrap();
+    assert_eq!(6, vtf.header.faces());
+    assert_eq!(data.len() - face_size, converted.len());
+    assert_eq!(
+        &data[data.len() - 7 * face_size..data.len() - face_size],
+        &converted[converted.len() - 6 * face_size..]
+    );
+
+    // converting back marks the spheremap face as missing
+    let restored = VTF::convert_version(&converted, [7, 4]).unwrap();
+    let vtf = vtf::from_bytes(&restored).unwrap();
+    assert_eq!(0xffff, vtf.header.first_frame);
+    assert_eq!(6, vtf.header.faces());
+    assert_eq!(converted.len(), restored.len());
+
+    // the first frame of a 7.5 environment map has no place in older versions
+    let mut animated = converted.clone();
+    animated[26..28].copy_from_slice(&1u16.to_le_bytes());
+    assert_eq!(1, vtf::from_bytes(&animated).unwrap().header.first_frame);
+    assert!(matches!(
+        VTF::convert_version(&animated, [7, 4]),
+        Err(Error::UnsupportedEnvmapFirstFrame(7, 4, 1))
+    ));
+    assert!(VTF::convert_version(&animated, [7, 5]).is_ok());
+}
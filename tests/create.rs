@@ -0,0 +1,153 @@
+use image::{DynamicImage, GenericImageView, ImageBuffer, Rgba, Rgba32FImage, RgbaImage};
+use vtf::encode::{AutoFormatOptions, EncodeOptions, Luminance};
+use vtf::vtf::VTF;
+use vtf::{ImageFormat, TextureFlags};
+
+fn test_image() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(8, 8, |x, y| {
+        Rgba([(x * 32) as u8, (y * 32) as u8, 128, (x * y * 4) as u8])
+    }))
+}
+
+#[test]
+fn test_create_i8() {
+    let data = vtf::create(test_image(), ImageFormat::I8).unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert_eq!(ImageFormat::I8, vtf.header.highres_image_format);
+
+    let image = vtf.highres_image.decode(0).unwrap().into_luma8();
+    assert_eq!((8, 8), image.dimensions());
+    // 0.299 * 32 + 0.587 * 64 + 0.114 * 128
+    assert_eq!(62, image.get_pixel(1, 2)[0]);
+}
+
+#[test]
+fn test_create_i8_single_channel() {
+    let options = EncodeOptions {
+        luminance: Luminance::Green,
+        ..Default::default()
+    };
+    let data = VTF::create_with_options(test_image(), ImageFormat::I8, &options).unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    let image = vtf.highres_image.decode(0).unwrap().into_luma8();
+    assert_eq!(64, image.get_pixel(1, 2)[0]);
+}
+
+#[test]
+fn test_create_ia88() {
+    let data = vtf::create(test_image(), ImageFormat::Ia88).unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    let image = vtf.highres_image.decode(0).unwrap().into_luma_alpha8();
+    assert_eq!([62, 8], image.get_pixel(1, 2).0);
+}
+
+#[test]
+fn test_create_a8() {
+    let data = vtf::create(test_image(), ImageFormat::A8).unwrap();
+    assert_eq!(64 + 8 * 8, data.len());
+
+    let vtf = vtf::from_bytes(&data).unwrap();
+    let image = vtf.highres_image.decode(0).unwrap();
+    assert_eq!((8, 8), image.dimensions());
+    assert_eq!(test_image().get_pixel(3, 5)[3], image.get_pixel(3, 5)[3]);
+}
+
+#[test]
+fn test_create_rgba16161616f() {
+    let source = DynamicImage::ImageRgba32F(Rgba32FImage::from_fn(4, 4, |x, y| {
+        Rgba([x as f32 * 4.5, y as f32 / 8.0, 0.125, 1.0])
+    }));
+    let data = vtf::create(source.clone(), ImageFormat::Rgba16161616f).unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    let image = vtf.highres_image.decode(0).unwrap().into_rgba32f();
+    assert_eq!(source.into_rgba32f(), image);
+}
+
+#[test]
+fn test_create_rgba16161616() {
+    let source = DynamicImage::ImageRgba16(ImageBuffer::from_fn(4, 4, |x, y| {
+        Rgba([x as u16 * 1001, y as u16 * 3, 40000, u16::MAX])
+    }));
+    let data = vtf::create(source.clone(), ImageFormat::Rgba16161616).unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    let image = vtf.highres_image.decode(0).unwrap();
+    assert_eq!(source, image);
+}
+
+#[test]
+fn test_create_auto() {
+    let opaque = RgbaImage::from_fn(4, 4, |x, _| Rgba([x as u8 * 60, 0, 0, 255]));
+    let one_bit = RgbaImage::from_fn(4, 4, |x, _| Rgba([x as u8 * 60, 0, 0, (x % 2) as u8 * 255]));
+    let eight_bit = RgbaImage::from_fn(4, 4, |x, _| Rgba([x as u8 * 60, 0, 0, x as u8 * 60]));
+    let gray = RgbaImage::from_fn(4, 4, |x, _| Rgba([x as u8, x as u8, x as u8, 255]));
+    let gray_alpha = RgbaImage::from_fn(4, 4, |x, _| Rgba([x as u8, x as u8, x as u8, 128]));
+
+    let cases = [
+        (
+            opaque.clone(),
+            false,
+            ImageFormat::Dxt1,
+            TextureFlags::empty(),
+        ),
+        (
+            one_bit.clone(),
+            false,
+            ImageFormat::Dxt1Onebitalpha,
+            TextureFlags::ONEBITALPHA,
+        ),
+        (
+            eight_bit.clone(),
+            false,
+            ImageFormat::Dxt5,
+            TextureFlags::EIGHTBITALPHA,
+        ),
+        (opaque, true, ImageFormat::Rgb888, TextureFlags::empty()),
+        (
+            one_bit,
+            true,
+            ImageFormat::Rgba8888,
+            TextureFlags::ONEBITALPHA,
+        ),
+        (
+            eight_bit,
+            true,
+            ImageFormat::Rgba8888,
+            TextureFlags::EIGHTBITALPHA,
+        ),
+        (gray, false, ImageFormat::I8, TextureFlags::empty()),
+        (
+            gray_alpha,
+            false,
+            ImageFormat::Ia88,
+            TextureFlags::EIGHTBITALPHA,
+        ),
+    ];
+    for (image, prefer_uncompressed, format, alpha_flags) in cases {
+        let data = VTF::create_auto(
+            DynamicImage::ImageRgba8(image),
+            &AutoFormatOptions {
+                prefer_uncompressed,
+            },
+        )
+        .unwrap();
+        let vtf = vtf::from_bytes(&data).unwrap();
+        assert_eq!(format, vtf.header.highres_image_format);
+        assert_eq!(
+            alpha_flags,
+            vtf.header.flags & (TextureFlags::ONEBITALPHA | TextureFlags::EIGHTBITALPHA)
+        );
+    }
+
+    let hdr = Rgba32FImage::from_pixel(4, 4, Rgba([2.0, 1.0, 0.5, 1.0]));
+    let data = VTF::create_auto(
+        DynamicImage::ImageRgba32F(hdr),
+        &AutoFormatOptions::default(),
+    )
+    .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    assert_eq!(ImageFormat::Rgba16161616f, vtf.header.highres_image_format);
+}
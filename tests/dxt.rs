@@ -0,0 +1,82 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use texpresso::{Algorithm, Format, Params, COLOUR_WEIGHTS_PERCEPTUAL};
+use vtf::encode::{encode, EncodeOptions};
+use vtf::image::VTFImage;
+use vtf::{ImageFormat, VTFBuilder};
+
+/// Size that isn't a multiple of the block size, so the last row and column of blocks are partial
+const WIDTH: u32 = 37;
+const HEIGHT: u32 = 21;
+
+fn test_image() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(WIDTH, HEIGHT, |x, y| {
+        Rgba([
+            (x * 7) as u8,
+            (y * 11) as u8,
+            ((x + y) * 5) as u8,
+            (x * y) as u8,
+        ])
+    }))
+}
+
+/// Compress and decompress the whole image at once, the output the row based and parallel
+/// code has to match
+fn reference(format: Format, image: &DynamicImage) -> (Vec<u8>, Vec<u8>) {
+    let (width, height) = (WIDTH as usize, HEIGHT as usize);
+    let params = Params {
+        algorithm: Algorithm::ClusterFit,
+        weights: COLOUR_WEIGHTS_PERCEPTUAL,
+        weigh_colour_by_alpha: false,
+    };
+    let mut compressed = vec![0; format.compressed_size(width, height)];
+    format.compress(
+        image.to_rgba8().as_raw(),
+        width,
+        height,
+        params,
+        &mut compressed,
+    );
+    let mut decompressed = vec![0; width * height * 4];
+    format.decompress(&compressed, width, height, &mut decompressed);
+    (compressed, decompressed)
+}
+
+#[test]
+fn test_dxt_partial_blocks() {
+    let image = test_image();
+    // any valid header works as template, only the size and format are used for decoding
+    let header = vtf::from_bytes(
+        &VTFBuilder::new(
+            image.resize_exact(4, 4, image::imageops::FilterType::Nearest),
+            ImageFormat::Rgba8888,
+        )
+        .build()
+        .unwrap(),
+    )
+    .unwrap()
+    .header;
+
+    for (format, variant) in [
+        (ImageFormat::Dxt1, Format::Bc1),
+        (ImageFormat::Dxt3, Format::Bc2),
+        (ImageFormat::Dxt5, Format::Bc3),
+    ] {
+        let (compressed, decompressed) = reference(variant, &image);
+        let encoded = encode(&image, format, &EncodeOptions::default()).unwrap();
+        assert_eq!(compressed, encoded, "{} encoding differs", format);
+
+        let mut header = header.clone();
+        header.width = WIDTH as u16;
+        header.height = HEIGHT as u16;
+        header.highres_image_format = format;
+        let decoded = VTFImage::new(header, format, WIDTH as u16, HEIGHT as u16, &encoded, 0)
+            .decode(0)
+            .unwrap();
+        assert_eq!(
+            decompressed,
+            decoded.to_rgba8().into_raw(),
+            "{} decoding differs",
+            format
+        );
+    }
+}
@@ -0,0 +1,37 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use vtf::{ImageFormat, TextureFlags, VTFBuilder};
+
+#[test]
+fn test_flags_display() {
+    assert_eq!(
+        "CLAMPS | CLAMPT | NOMIP | NOLOD | EIGHTBITALPHA",
+        TextureFlags::from_bits_retain(8972).to_string()
+    );
+    assert_eq!(
+        "SRGB | 0x100000",
+        TextureFlags::from_bits_retain(0x10_0040).to_string()
+    );
+    assert_eq!(
+        "SINGLECOPY | PRE_SRGB",
+        TextureFlags::from_bits_retain(0xc_0000).to_string()
+    );
+    assert_eq!("0x0", TextureFlags::empty().to_string());
+    assert_eq!(
+        "CLAMPS | NOMIP",
+        format!("{:?}", TextureFlags::CLAMPS | TextureFlags::NOMIP)
+    );
+}
+
+#[test]
+fn test_flags_unknown_bits() {
+    let flags = TextureFlags::ENVMAP | TextureFlags::from_bits_retain(0x8000_0000);
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255])));
+    let data = VTFBuilder::new(image, ImageFormat::Rgba8888)
+        .flags(flags)
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    assert_eq!(0x8000_4000, vtf.header.flags.bits());
+    assert!(vtf.header.flags.contains(TextureFlags::ENVMAP));
+}
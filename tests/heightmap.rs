@@ -0,0 +1,66 @@
+use image::{DynamicImage, GrayImage, Luma};
+use vtf::heightmap::{height_to_normal, height_to_ssbump, GradientKernel, HeightMapOptions};
+use vtf::{ImageFormat, TextureFlags, VTFBuilder};
+
+/// A ramp going up to the right
+fn ramp() -> DynamicImage {
+    DynamicImage::ImageLuma8(GrayImage::from_fn(16, 16, |x, _| Luma([(x * 16) as u8])))
+}
+
+#[test]
+fn test_height_to_normal() {
+    let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([100])));
+    let normal = height_to_normal(&flat, &HeightMapOptions::default()).into_rgba8();
+    assert_eq!([128, 128, 255, 255], normal[(1, 1)].0);
+
+    for kernel in [
+        GradientKernel::CentralDifference,
+        GradientKernel::Prewitt,
+        GradientKernel::Sobel,
+        GradientKernel::Scharr,
+    ] {
+        let options = HeightMapOptions {
+            kernel,
+            ..Default::default()
+        };
+        let normal = height_to_normal(&ramp(), &options).into_rgba8();
+        // the slope faces left, without any vertical component
+        assert!(normal[(8, 8)][0] < 128);
+        assert_eq!(128, normal[(8, 8)][1]);
+    }
+}
+
+#[test]
+fn test_height_to_ssbump() {
+    let flat = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 4, Luma([100])));
+    let ssbump = height_to_ssbump(&flat, &HeightMapOptions::default()).into_rgb8();
+    assert_eq!([147, 147, 147], ssbump[(1, 1)].0);
+
+    // light from the right is blocked by the slope, light from the left hits it
+    let ssbump = height_to_ssbump(&ramp(), &HeightMapOptions::default()).into_rgb8();
+    let pixel = ssbump[(8, 8)];
+    assert!(pixel[0] < 147);
+    assert!(pixel[1] > 147);
+    assert_eq!(pixel[1], pixel[2]);
+}
+
+#[test]
+fn test_height_map_flags() {
+    let data = VTFBuilder::normal_from_height(&ramp(), ImageFormat::Rgba8888, &Default::default())
+        .flags(TextureFlags::empty())
+        .build()
+        .unwrap();
+    assert_eq!(
+        TextureFlags::NORMAL,
+        vtf::from_bytes(&data).unwrap().header.flags
+    );
+
+    let data = VTFBuilder::ssbump_from_height(&ramp(), ImageFormat::Rgb888, &Default::default())
+        .flags(TextureFlags::empty())
+        .build()
+        .unwrap();
+    assert_eq!(
+        TextureFlags::SSBUMP,
+        vtf::from_bytes(&data).unwrap().header.flags
+    );
+}
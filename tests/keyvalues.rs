@@ -0,0 +1,48 @@
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba};
+use vtf::keyvalues::{KeyValues, Value};
+use vtf::{Error, ImageFormat, VTFBuilder};
+
+#[test]
+fn test_keyvalues_serialize() {
+    let input = "\"source\" \"materials/brick.tga\"\n\"build\"\n{\n\t\"artist\" \"someone\"\n\t\"hash\" \"abc123\"\n}\n";
+    let keyvalues = KeyValues::parse(input).unwrap();
+    assert_eq!(input, keyvalues.to_string());
+    assert_eq!(keyvalues, KeyValues::parse(&keyvalues.to_string()).unwrap());
+    assert_eq!(keyvalues.to_string(), keyvalues.serialize().unwrap());
+
+    let quoted = KeyValues {
+        entries: vec![("a".into(), Value::String("x\"y".into()))],
+    };
+    assert!(matches!(
+        quoted.serialize(),
+        Err(Error::InvalidKeyValues(_))
+    ));
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 4, Rgb([1, 2, 3])));
+    assert!(matches!(
+        VTFBuilder::new(image, ImageFormat::Rgb888)
+            .version([7, 4])
+            .key_values(quoted)
+            .build(),
+        Err(Error::InvalidKeyValues(_))
+    ));
+}
+
+#[test]
+fn test_keyvalues_resource() {
+    let keyvalues = KeyValues::parse("source \"brick.tga\" build { hash abc123 }").unwrap();
+    let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(4, 4, Rgb([1, 2, 3])));
+    let data = VTFBuilder::new(image, ImageFormat::Rgb888)
+        .version([7, 4])
+        .mipmaps(true)
+        .thumbnail(true)
+        .key_values(keyvalues.clone())
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    assert_eq!(Some(keyvalues), vtf.key_values().unwrap());
+    assert_eq!(
+        Rgba([1, 2, 3, 255]),
+        vtf.highres_image.decode(0).unwrap().get_pixel(3, 3)
+    );
+}
@@ -0,0 +1,187 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, Rgba, RgbaImage};
+use vtf::image::VTFImage;
+use vtf::mipmap::{generate_mipmaps, mip_dimensions, AddressMode, MipmapOptions, NormalMapOptions};
+use vtf::{Error, ImageFormat, VTFBuilder};
+
+fn coverage(image: &DynamicImage, cutoff: u8) -> f32 {
+    let image = image.to_rgba8();
+    let covered = image.pixels().filter(|pixel| pixel[3] > cutoff).count();
+    covered as f32 / (image.width() * image.height()) as f32
+}
+
+/// Noisy alpha like foliage, where a quarter of the pixels pass an alpha test at 0.75
+fn foliage() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(64, 64, |x, y| {
+        let hash = ((x * 73) ^ (y * 151)).wrapping_mul(2654435761);
+        Rgba([0, 255, 0, (hash >> 24) as u8])
+    }))
+}
+
+#[test]
+fn test_alpha_coverage() {
+    let options = MipmapOptions {
+        filter: FilterType::Triangle,
+        ..Default::default()
+    };
+    let levels = generate_mipmaps(&foliage(), 4, &options);
+    let target = coverage(&levels[0], 191);
+    assert!((target - 0.25).abs() < 0.05);
+    assert!(coverage(&levels[3], 191) < 0.1);
+
+    let options = MipmapOptions {
+        alpha_coverage: Some(0.75),
+        ..options
+    };
+    let levels = generate_mipmaps(&foliage(), 4, &options);
+    for level in &levels {
+        assert!((coverage(level, 191) - target).abs() < 0.05);
+    }
+}
+
+/// Decode every mip level of the first frame, from largest to smallest
+fn decode_mipmaps(data: &[u8]) -> Vec<DynamicImage> {
+    let header = vtf::from_bytes(data).unwrap().header;
+    let format = header.highres_image_format;
+    let mut offset = data.len();
+    (0..header.mipmap_count)
+        .map(|level| {
+            let (width, height) = mip_dimensions(header.width as u32, header.height as u32, level);
+            offset -= format.frame_size(width, height).unwrap() as usize;
+            let mut mip_header = header.clone();
+            mip_header.mipmap_count = 1;
+            VTFImage::new(
+                mip_header,
+                format,
+                width as u16,
+                height as u16,
+                data,
+                offset,
+            )
+            .decode(0)
+            .unwrap()
+        })
+        .collect()
+}
+
+#[test]
+fn test_alpha_coverage_dxt() {
+    for (format, cutoff, reference) in [
+        (ImageFormat::Dxt5, 0.75, 191),
+        (ImageFormat::Dxt1Onebitalpha, 0.5, 127),
+        (ImageFormat::Dxt1Onebitalpha, 0.75, 191),
+    ] {
+        let options = MipmapOptions {
+            alpha_coverage: Some(cutoff),
+            ..Default::default()
+        };
+        let data = VTFBuilder::new(foliage(), format)
+            .mipmaps(true)
+            .mipmap_options(options)
+            .build()
+            .unwrap();
+        let levels = decode_mipmaps(&data);
+        let target = coverage(&levels[0], reference);
+        for level in &levels[..4] {
+            assert!(
+                (coverage(level, reference) - target).abs() < 0.1,
+                "{format} coverage {} instead of {target}",
+                coverage(level, reference)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_alpha_coverage_invalid_cutoff() {
+    for cutoff in [0.0, 1.0, -0.5, f32::NAN] {
+        let result = VTFBuilder::new(foliage(), ImageFormat::Dxt5)
+            .mipmaps(true)
+            .mipmap_options(MipmapOptions {
+                alpha_coverage: Some(cutoff),
+                ..Default::default()
+            })
+            .build();
+        assert!(matches!(result, Err(Error::InvalidAlphaCutoff(_))));
+    }
+}
+
+#[test]
+fn test_gamma_correct() {
+    // a checkerboard of black and white averages to middle gray in linear space
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 2, |x, y| {
+        if (x + y) % 2 == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    }));
+    let options = MipmapOptions {
+        filter: FilterType::Triangle,
+        ..Default::default()
+    };
+
+    let levels = generate_mipmaps(&image, 2, &options);
+    assert_eq!(Rgba([128, 128, 128, 128]), levels[1].to_rgba8()[(0, 0)]);
+
+    let options = MipmapOptions {
+        gamma_correct: Some(true),
+        ..options
+    };
+    let levels = generate_mipmaps(&image, 2, &options);
+    assert_eq!(Rgba([188, 188, 188, 128]), levels[1].to_rgba8()[(0, 0)]);
+}
+
+#[test]
+fn test_address_mode() {
+    // white on the left edge, black everywhere else
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(16, 16, |x, _| {
+        if x == 0 {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 255])
+        }
+    }));
+    let options = MipmapOptions {
+        filter: FilterType::Triangle,
+        ..Default::default()
+    };
+
+    let levels = generate_mipmaps(&image, 2, &options);
+    let clamped = levels[1].to_rgba8();
+    assert_eq!(0, clamped[(7, 0)][0]);
+
+    let options = MipmapOptions {
+        address_u: Some(AddressMode::Wrap),
+        ..options
+    };
+    let levels = generate_mipmaps(&image, 2, &options);
+    let wrapped = levels[1].to_rgba8();
+    assert!(wrapped[(7, 0)][0] > 0);
+    assert!(wrapped[(0, 0)][0] < clamped[(0, 0)][0]);
+    assert_eq!(clamped[(3, 0)], wrapped[(3, 0)]);
+}
+
+#[test]
+fn test_normal_map() {
+    // alternating normals tilted left and right average to a shortened normal pointing up
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(2, 2, |x, _| {
+        if x == 0 {
+            Rgba([37, 128, 218, 255])
+        } else {
+            Rgba([218, 128, 218, 255])
+        }
+    }));
+    let options = MipmapOptions {
+        filter: FilterType::Triangle,
+        normal_map: Some(NormalMapOptions {
+            toksvig_power: Some(16.0),
+        }),
+        ..Default::default()
+    };
+
+    let levels = generate_mipmaps(&image, 2, &options);
+    let pixel = levels[1].to_rgba8()[(0, 0)];
+    assert_eq!([128, 128, 255], [pixel[0], pixel[1], pixel[2]]);
+    assert!(pixel[3] < 128);
+}
@@ -0,0 +1,235 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use vtf::flags::ExtendedFlags;
+use vtf::resources::{LodControl, Resource, ResourceId, ResourceType};
+use vtf::vtf::VTF;
+use vtf::{Error, ImageFormat, VTFBuilder};
+
+/// Add a resource with a data chunk to a 7.3+ file built without extra resources
+fn add_resource(data: &[u8], id: [u8; 3], payload: &[u8]) -> Vec<u8> {
+    let mut vtf = vtf::from_bytes(data).unwrap().header;
+    let header_size = vtf.size();
+    let image_data = &data[header_size..];
+
+    vtf.resources
+        .resources
+        .push(Resource::new(ResourceType { id, flags: 0 }, 0));
+    let new_size = vtf.size() as u32;
+    let chunk_size = payload.len() as u32 + 4;
+    for resource in &mut vtf.resources.resources {
+        resource.data = match resource.ty.resource_id() {
+            ResourceId::LowResImage | ResourceId::Image => {
+                resource.data - header_size as u32 + new_size + chunk_size
+            }
+            _ => new_size,
+        };
+    }
+
+    let mut out = Vec::new();
+    vtf.write(&mut out).unwrap();
+    out.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    out.extend_from_slice(payload);
+    out.extend_from_slice(image_data);
+    out
+}
+
+#[test]
+fn test_resource_data() {
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 4])));
+    let data = VTFBuilder::new(image, ImageFormat::Rgba8888)
+        .version([7, 4])
+        .thumbnail(true)
+        .build()
+        .unwrap();
+    let data = add_resource(&data, *b"KVD", b"\"key\" \"value\"");
+
+    let vtf = vtf::from_bytes(&data).unwrap();
+    let ids: Vec<_> = vtf
+        .header
+        .resources
+        .resources
+        .iter()
+        .map(|resource| resource.ty.resource_id())
+        .collect();
+    assert_eq!(
+        vec![
+            ResourceId::LowResImage,
+            ResourceId::Image,
+            ResourceId::KeyValues
+        ],
+        ids
+    );
+    assert_eq!(
+        Some(&b"\"key\" \"value\""[..]),
+        vtf.resource_data(ResourceId::KeyValues).unwrap()
+    );
+    assert_eq!(None, vtf.resource_data(ResourceId::Image).unwrap());
+    assert_eq!(None, vtf.resource_data(ResourceId::Crc).unwrap());
+    assert_eq!(
+        Rgba([1, 2, 3, 4]),
+        image::GenericImageView::get_pixel(&vtf.highres_image.decode(0).unwrap(), 0, 0)
+    );
+
+    // chunks are kept when converting between versions with resources
+    let converted = VTF::convert_version(&data, [7, 5]).unwrap();
+    let vtf = vtf::from_bytes(&converted).unwrap();
+    assert_eq!(
+        Some(&b"\"key\" \"value\""[..]),
+        vtf.resource_data(ResourceId::KeyValues).unwrap()
+    );
+}
+
+#[test]
+fn test_resource_id() {
+    for id in [
+        ResourceId::LowResImage,
+        ResourceId::Image,
+        ResourceId::Sheet,
+        ResourceId::Crc,
+        ResourceId::LodControl,
+        ResourceId::ExtendedFlags,
+        ResourceId::KeyValues,
+        ResourceId::Unknown(*b"ABC"),
+    ] {
+        assert_eq!(id, ResourceId::from_id(id.id()));
+    }
+    assert_eq!(
+        ResourceId::Image,
+        ResourceType::VTF_LEGACY_RSRC_IMAGE.resource_id()
+    );
+    assert_eq!(ResourceId::ExtendedFlags, ResourceId::from_id(*b"TSO"));
+    assert_eq!(ResourceId::Unknown(*b"TS0"), ResourceId::from_id(*b"TS0"));
+}
+
+#[test]
+fn test_crc() {
+    assert_eq!(0xcbf4_3926, vtf::crc::crc32(b"123456789"));
+    assert_eq!(0, vtf::crc::crc32(b""));
+
+    let source = b"source image";
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 4])));
+    for version in [[7, 3], [7, 5]] {
+        let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+            .version(version)
+            .crc(vtf::crc::crc32(source))
+            .build()
+            .unwrap();
+        let vtf = vtf::from_bytes(&data).unwrap();
+
+        assert_eq!(Some(true), vtf.header.verify_crc(source));
+        assert_eq!(Some(false), vtf.header.verify_crc(b"changed"));
+        assert_eq!(None, vtf.resource_data(ResourceId::Crc).unwrap());
+    }
+
+    let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+        .version([7, 2])
+        .build()
+        .unwrap();
+    assert_eq!(
+        None,
+        vtf::from_bytes(&data).unwrap().header.verify_crc(source)
+    );
+    assert!(matches!(
+        VTFBuilder::new(image, ImageFormat::Rgba8888)
+            .version([7, 2])
+            .crc(0)
+            .build(),
+        Err(Error::ResourcesNotSupported(7, 2))
+    ));
+}
+
+#[test]
+fn test_lod_control() {
+    let lod = LodControl {
+        resolution_clamp_u: 8,
+        resolution_clamp_v: 7,
+        ..Default::default()
+    };
+    assert_eq!(0x0708, u32::from(lod));
+    // the Xbox 360 clamps survive a round trip
+    assert_eq!(0x0506_0708, u32::from(LodControl::from(0x0506_0708)));
+    assert_eq!(5, LodControl::from(0x0506_0708).resolution_clamp_v_360);
+
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 4])));
+    let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+        .version([7, 4])
+        .lod_control(lod)
+        .build()
+        .unwrap();
+    assert_eq!(
+        Some(lod),
+        vtf::from_bytes(&data).unwrap().header.lod_control()
+    );
+
+    // editing an existing file
+    let data = VTFBuilder::new(image, ImageFormat::Rgba8888)
+        .version([7, 5])
+        .thumbnail(true)
+        .build()
+        .unwrap();
+    assert_eq!(None, vtf::from_bytes(&data).unwrap().header.lod_control());
+
+    let edited = VTF::set_resource_value(&data, ResourceType::LOD_CONTROL, lod.into()).unwrap();
+    let vtf = vtf::from_bytes(&edited).unwrap();
+    assert_eq!(Some(lod), vtf.header.lod_control());
+    assert_eq!(3, vtf.header.resources.resources.len());
+    assert_eq!(data.len() + 8, edited.len());
+    assert_eq!(
+        Rgba([1, 2, 3, 4]),
+        image::GenericImageView::get_pixel(&vtf.highres_image.decode(0).unwrap(), 3, 3)
+    );
+
+    let smaller = LodControl {
+        resolution_clamp_u: 2,
+        resolution_clamp_v: 2,
+        resolution_clamp_u_360: 1,
+        resolution_clamp_v_360: 1,
+    };
+    let edited =
+        VTF::set_resource_value(&edited, ResourceType::LOD_CONTROL, smaller.into()).unwrap();
+    let vtf = vtf::from_bytes(&edited).unwrap();
+    assert_eq!(Some(smaller), vtf.header.lod_control());
+    assert_eq!(3, vtf.header.resources.resources.len());
+
+    let old = VTF::convert_version(&data, [7, 2]).unwrap();
+    assert!(matches!(
+        VTF::set_resource_value(&old, ResourceType::LOD_CONTROL, lod.into()),
+        Err(Error::ResourcesNotSupported(7, 2))
+    ));
+}
+
+#[test]
+fn test_extended_flags() {
+    let flags = ExtendedFlags::from_bits_retain(0x0000_0101);
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 4])));
+    let data = VTFBuilder::new(image, ImageFormat::Rgba8888)
+        .version([7, 3])
+        .extended_flags(flags)
+        .build()
+        .unwrap();
+    let header = vtf::from_bytes(&data).unwrap().header;
+
+    assert_eq!(Some(flags), header.extended_flags());
+    assert!(format!("{:?}", header).contains("extended_flags: Some(0x101)"));
+
+    let cleared = VTF::set_resource_value(&data, ResourceType::EXTENDED_FLAGS, 0).unwrap();
+    let header = vtf::from_bytes(&cleared).unwrap().header;
+    assert_eq!(Some(ExtendedFlags::empty()), header.extended_flags());
+
+    // entries with extra resource flag bits are matched by id and edited in place
+    let mut other = data.clone();
+    let position = other.windows(4).position(|id| id == b"TSO\x02").unwrap();
+    other[position + 3] = 0x03;
+    let cleared = VTF::set_resource_value(&other, ResourceType::EXTENDED_FLAGS, 0).unwrap();
+    let header = vtf::from_bytes(&cleared).unwrap().header;
+    assert_eq!(Some(ExtendedFlags::empty()), header.extended_flags());
+    assert_eq!(other.len(), cleared.len());
+    assert_eq!(
+        1,
+        header
+            .resources
+            .resources
+            .iter()
+            .filter(|resource| resource.ty.resource_id() == ResourceId::ExtendedFlags)
+            .count()
+    );
+}
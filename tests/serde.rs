@@ -0,0 +1,29 @@
+#![cfg(feature = "serde")]
+
+use image::{DynamicImage, Rgba, RgbaImage};
+use vtf::header::VTFHeader;
+use vtf::{ImageFormat, TextureFlags, VTFBuilder};
+
+#[test]
+fn test_serde_header() {
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([1, 2, 3, 255])));
+    let data = VTFBuilder::new(image, ImageFormat::Dxt5)
+        .version([7, 3])
+        .flags(TextureFlags::CLAMPS | TextureFlags::NOMIP)
+        .build()
+        .unwrap();
+    let header = vtf::from_bytes(&data).unwrap().header;
+
+    let json = serde_json::to_value(&header).unwrap();
+    assert_eq!("Dxt5", json["highres_image_format"]);
+    assert_eq!("CLAMPS | NOMIP", json["flags"]);
+    assert_eq!(1, json["resources"]["resources"].as_array().unwrap().len());
+
+    let parsed: VTFHeader = serde_json::from_value(json).unwrap();
+    assert_eq!(header.flags, parsed.flags);
+    assert_eq!(header.highres_image_format, parsed.highres_image_format);
+    assert_eq!(
+        header.resources.resources[0].ty,
+        parsed.resources.resources[0].ty
+    );
+}
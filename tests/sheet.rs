@@ -0,0 +1,92 @@
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
+use vtf::sheet::{Sheet, SheetFrame, SheetSequence, UvRect};
+use vtf::{Error, ImageFormat, VTFBuilder};
+
+/// A 4x1 strip of 8x8 sprites with a different color each
+fn atlas() -> DynamicImage {
+    DynamicImage::ImageRgba8(RgbaImage::from_fn(32, 8, |x, _| {
+        Rgba([(x / 8 * 60) as u8, 0, 0, 255])
+    }))
+}
+
+fn frame(index: u32, duration: f32, coords: usize) -> SheetFrame {
+    // inset by half a texel, like mksheet does
+    let rect = UvRect {
+        u0: (index as f32 * 8.0 + 0.5) / 32.0,
+        v0: 0.5 / 8.0,
+        u1: (index as f32 * 8.0 + 7.5) / 32.0,
+        v1: 7.5 / 8.0,
+    };
+    SheetFrame {
+        duration,
+        coords: vec![rect; coords],
+    }
+}
+
+fn sheet(version: u32) -> Sheet {
+    let coords = if version == 0 { 1 } else { 4 };
+    Sheet {
+        version,
+        sequences: vec![
+            SheetSequence {
+                id: 0,
+                clamp: false,
+                total_time: 0.3,
+                frames: (0..3).map(|index| frame(index, 0.1, coords)).collect(),
+            },
+            SheetSequence {
+                id: 5,
+                clamp: true,
+                total_time: 1.0,
+                frames: vec![frame(3, 1.0, coords)],
+            },
+        ],
+    }
+}
+
+#[test]
+fn test_sheet_round_trip() {
+    for version in [0, 1] {
+        let sheet = sheet(version);
+        let mut data = Vec::new();
+        sheet.write(&mut data).unwrap();
+        assert_eq!(sheet, Sheet::read(&mut data.as_slice()).unwrap());
+    }
+
+    let mut invalid = sheet(0);
+    invalid.version = 1;
+    assert!(matches!(
+        invalid.write(&mut Vec::new()),
+        Err(Error::InvalidSheet(_))
+    ));
+    assert!(matches!(
+        Sheet::read(&mut &[2, 0, 0, 0, 0, 0, 0, 0][..]),
+        Err(Error::InvalidSheet(_))
+    ));
+}
+
+#[test]
+fn test_sheet_resource() {
+    let data = VTFBuilder::new(atlas(), ImageFormat::Rgba8888)
+        .version([7, 5])
+        .sheet(sheet(1))
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+    let sheet = vtf.sheet().unwrap().unwrap();
+    assert_eq!(2, sheet.sequences.len());
+
+    let image = vtf.highres_image.decode(0).unwrap();
+    let frames = sheet.sequence(0).unwrap().extract(&image);
+    assert_eq!(3, frames.len());
+    for (index, (sprite, duration)) in frames.iter().enumerate() {
+        assert_eq!((8, 8), sprite.dimensions());
+        assert_eq!(0.1, *duration);
+        assert_eq!(Rgba([index as u8 * 60, 0, 0, 255]), sprite.get_pixel(0, 0));
+        assert_eq!(Rgba([index as u8 * 60, 0, 0, 255]), sprite.get_pixel(7, 7));
+    }
+
+    let last = sheet.sequence(5).unwrap().frames[0].coords[0].crop(&image);
+    assert_eq!(Rgba([180, 0, 0, 255]), last.get_pixel(4, 4));
+    assert!(sheet.sequence(1).is_none());
+}
@@ -0,0 +1,102 @@
+use image::{DynamicImage, Rgba, RgbaImage};
+use vtf::header::ReadMode;
+use vtf::vtf::VTF;
+use vtf::{Error, ImageFormat, VTFBuilder};
+
+fn test_file(version: [u32; 2]) -> Vec<u8> {
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([1, 2, 3, 4])));
+    VTFBuilder::new(image, ImageFormat::Rgba8888)
+        .version(version)
+        .mipmaps(true)
+        .build()
+        .unwrap()
+}
+
+fn read(data: &[u8], mode: ReadMode) -> Result<(), Error> {
+    VTF::read_with_mode(data, mode).map(|_| ())
+}
+
+#[test]
+fn test_validate_valid() {
+    for minor in 0..=5 {
+        let data = test_file([7, minor]);
+        read(&data, ReadMode::Strict).unwrap();
+        read(&data, ReadMode::Lenient).unwrap();
+    }
+}
+
+#[test]
+fn test_validate_dimensions() {
+    let mut data = test_file([7, 1]);
+    data[16..18].copy_from_slice(&0u16.to_le_bytes());
+    assert!(matches!(
+        vtf::from_bytes(&data),
+        Err(Error::InvalidDimensions(0, 8))
+    ));
+}
+
+#[test]
+fn test_validate_mipmap_count() {
+    let mut data = test_file([7, 1]);
+    data[56] = 5;
+    assert!(matches!(
+        vtf::from_bytes(&data),
+        Err(Error::InvalidMipmapCount(5, 4))
+    ));
+}
+
+#[test]
+fn test_validate_header_size() {
+    let mut data = test_file([7, 2]);
+    data[12..16].copy_from_slice(&96u32.to_le_bytes());
+    assert!(matches!(
+        read(&data, ReadMode::Strict),
+        Err(Error::InvalidHeaderSize(96, 80))
+    ));
+    // the image data is now read from the wrong offset, but stays within the file
+    data.extend_from_slice(&[0; 16]);
+    read(&data, ReadMode::Lenient).unwrap();
+}
+
+#[test]
+fn test_validate_resource_offset() {
+    let mut data = test_file([7, 3]);
+    // offset of the image resource in the resource directory
+    data[84..88].copy_from_slice(&10_000u32.to_le_bytes());
+    assert!(matches!(
+        vtf::from_bytes(&data),
+        Err(Error::ResourceOutOfBounds(_, 10_000))
+    ));
+}
+
+#[test]
+fn test_validate_truncated() {
+    let data = test_file([7, 1]);
+    assert!(matches!(
+        vtf::from_bytes(&data[..data.len() - 1]),
+        Err(Error::ImageDataOutOfBounds(_, _))
+    ));
+}
+
+#[test]
+fn test_validate_maximum_size() {
+    for format in [ImageFormat::Rgba16161616, ImageFormat::Rgba32323232f] {
+        let mut data = test_file([7, 2]);
+        data[16..20].copy_from_slice(&[0xff; 4]);
+        data[52..56].copy_from_slice(&(format as i16 as u32).to_le_bytes());
+        data.resize(320, 0);
+        assert!(matches!(
+            vtf::from_bytes(&data),
+            Err(Error::ImageDataOutOfBounds(_, 320))
+        ));
+
+        // every size field at its maximum overflows even a u64
+        data[24..26].copy_from_slice(&[0xff; 2]);
+        data[63..65].copy_from_slice(&[0xff; 2]);
+        data[56] = 16;
+        assert!(matches!(
+            vtf::from_bytes(&data),
+            Err(Error::ImageDataOutOfBounds(u64::MAX, 320))
+        ));
+    }
+}
@@ -0,0 +1,182 @@
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage, Rgba, RgbaImage};
+use std::path::PathBuf;
+use vtf::keyvalues::{KeyValues, Value};
+use vtf::vtex::CompileParams;
+use vtf::{Error, ImageFormat, TextureFlags, VTFBuilder};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("vtf-test-{}-{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn test_keyvalues() {
+    let keyvalues = KeyValues::parse(
+        r#"
+        // comment
+        "Root"
+        {
+            key value
+            "Quoted Key" "C:\path\file" [$WIN32]
+            nested { inner 1 }
+        }
+        "#,
+    )
+    .unwrap();
+
+    let root = keyvalues.get_section("root").unwrap();
+    assert_eq!(Some("value"), root.get_str("KEY"));
+    assert_eq!(Some(r"C:\path\file"), root.get_str("quoted key"));
+    assert_eq!(
+        Some(&Value::String("1".into())),
+        root.get_section("nested").unwrap().get("inner")
+    );
+
+    assert!(matches!(
+        KeyValues::parse("key { value"),
+        Err(Error::InvalidKeyValues(_))
+    ));
+    assert!(matches!(
+        KeyValues::parse("key value }"),
+        Err(Error::InvalidKeyValues(_))
+    ));
+}
+
+#[test]
+fn test_compile_params() {
+    let params = CompileParams::parse(
+        "\"nomip\" \"1\"\n\"clamps\" 1\nclampt 0\nskybox 1\nbumpscale 2.5\nstartframe 0\nendframe 3\nunknown 1",
+    )
+    .unwrap();
+
+    assert!(params.nomip);
+    assert!(params.clamps);
+    assert!(!params.clampt);
+    assert!(params.skybox);
+    assert_eq!(Some(2.5), params.bumpscale);
+    assert_eq!((Some(0), Some(3)), (params.start_frame, params.end_frame));
+    assert_eq!(
+        TextureFlags::CLAMPS | TextureFlags::CLAMPT | TextureFlags::NOMIP,
+        params.flags()
+    );
+
+    assert!(matches!(
+        CompileParams::parse("bumpscale big"),
+        Err(Error::InvalidCompileParameter(key, value)) if key == "bumpscale" && value == "big"
+    ));
+}
+
+#[test]
+fn test_compile_params_builder() {
+    let opaque = DynamicImage::ImageRgb8(RgbImage::from_pixel(8, 8, Rgb([10, 20, 30])));
+    let params = CompileParams::parse("nocompress 1\nnolod 1\nnormal 1").unwrap();
+    let data = VTFBuilder::new(opaque, ImageFormat::Dxt5)
+        .compile_params(&params)
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&data).unwrap();
+
+    assert_eq!(ImageFormat::Bgr888, vtf.header.highres_image_format);
+    assert_eq!(TextureFlags::NORMAL | TextureFlags::NOLOD, vtf.header.flags);
+    assert_eq!(4, vtf.header.mipmap_count);
+    assert_eq!(
+        Rgba([10, 20, 30, 255]),
+        vtf.highres_image.decode(0).unwrap().get_pixel(0, 0)
+    );
+
+    // the alpha values decide the format, not whether the image has an alpha channel
+    let cases = [
+        (255, false, ImageFormat::Dxt1, TextureFlags::empty()),
+        (0, false, ImageFormat::Dxt5, TextureFlags::ONEBITALPHA),
+        (128, false, ImageFormat::Dxt5, TextureFlags::EIGHTBITALPHA),
+        (255, true, ImageFormat::Bgr888, TextureFlags::empty()),
+        (
+            128,
+            true,
+            ImageFormat::Bgra8888,
+            TextureFlags::EIGHTBITALPHA,
+        ),
+    ];
+    for (alpha, nocompress, format, flags) in cases {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 4, |x, _| {
+            Rgba([10, 20, 30, if x == 0 { alpha } else { 255 }])
+        }));
+        let params = CompileParams {
+            nocompress,
+            nomip: true,
+            ..Default::default()
+        };
+        let data = VTFBuilder::new(image.clone(), ImageFormat::Rgba8888)
+            .compile_params(&params)
+            .build()
+            .unwrap();
+        let vtf = vtf::from_bytes(&data).unwrap();
+
+        assert_eq!(format, vtf.header.highres_image_format);
+        assert_eq!(TextureFlags::NOMIP | flags, vtf.header.flags);
+        if nocompress {
+            assert_eq!(
+                image.to_rgba8(),
+                vtf.highres_image.decode(0).unwrap().to_rgba8()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_from_path() {
+    let dir = temp_dir("from-path");
+    let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(4, 4, Rgba([1, 2, 3, 128])));
+    image.save(dir.join("plain.png")).unwrap();
+    image.save(dir.join("sidecar.png")).unwrap();
+    std::fs::write(dir.join("sidecar.txt"), "nomip 1\nclamps 1").unwrap();
+    for frame in 1..=3 {
+        image
+            .save(dir.join(format!("anim{:03}.png", frame)))
+            .unwrap();
+    }
+    std::fs::write(dir.join("anim.txt"), "startframe 1\nendframe 3").unwrap();
+
+    let vtf_data = VTFBuilder::from_path(dir.join("plain.png"))
+        .unwrap()
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&vtf_data).unwrap();
+    assert_eq!(ImageFormat::Dxt5, vtf.header.highres_image_format);
+    assert_eq!(TextureFlags::EIGHTBITALPHA, vtf.header.flags);
+    assert_eq!(3, vtf.header.mipmap_count);
+
+    for path in ["sidecar.png", "sidecar.txt"] {
+        let vtf_data = VTFBuilder::from_path(dir.join(path))
+            .unwrap()
+            .build()
+            .unwrap();
+        let vtf = vtf::from_bytes(&vtf_data).unwrap();
+        assert_eq!(
+            TextureFlags::CLAMPS | TextureFlags::NOMIP | TextureFlags::EIGHTBITALPHA,
+            vtf.header.flags
+        );
+        assert_eq!(1, vtf.header.mipmap_count);
+    }
+
+    let vtf_data = VTFBuilder::from_path(dir.join("anim.txt"))
+        .unwrap()
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&vtf_data).unwrap();
+    assert_eq!(3, vtf.header.frames);
+
+    let vtf_data = VTFBuilder::from_path(dir.join("plain.png"))
+        .unwrap()
+        .build()
+        .unwrap();
+    let vtf = vtf::from_bytes(&vtf_data).unwrap();
+    assert_eq!([7, 5], vtf.header.version);
+    let source = std::fs::read(dir.join("plain.png")).unwrap();
+    assert_eq!(Some(true), vtf.header.verify_crc(&source));
+
+    assert!(VTFBuilder::from_path(dir.join("missing.txt")).is_err());
+
+    std::fs::remove_dir_all(dir).unwrap();
+}